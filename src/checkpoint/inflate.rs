@@ -0,0 +1,474 @@
+//! A deflate decoder which can be resumed at the start of any block.
+//!
+//! Nothing but the last 32 KiB of output carries over from one deflate block to the next, so
+//! decoding can resume at a block from its bit offset in the compressed data and that window.
+
+use super::invalid_data;
+use std::io::{self, BufRead};
+
+const WINDOW_SIZE: usize = 32768;
+const WINDOW_MASK: usize = WINDOW_SIZE - 1;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order in which the code lengths of the code length alphabet are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// LSB-first bit reader which keeps count of the bytes it took from the input.
+struct Bits {
+    buffer: u64,
+    count: u32,
+    bytes_read: u64,
+}
+
+impl Bits {
+    fn fill(&mut self, input: &mut dyn BufRead, n: u32) -> io::Result<()> {
+        while self.count < n {
+            let data = input.fill_buf()?;
+            if data.is_empty() {
+                break;
+            }
+            let take = (((64 - self.count) / 8) as usize).min(data.len());
+            for &byte in &data[..take] {
+                self.buffer |= (byte as u64) << self.count;
+                self.count += 8;
+            }
+            input.consume(take);
+            self.bytes_read += take as u64;
+        }
+        Ok(())
+    }
+
+    /// Returns the next `n` bits without consuming them, padded with zeros at the end of input.
+    fn peek(&mut self, input: &mut dyn BufRead, n: u32) -> io::Result<u32> {
+        self.fill(input, n)?;
+        Ok((self.buffer & ((1 << n) - 1)) as u32)
+    }
+
+    fn consume(&mut self, n: u32) -> io::Result<()> {
+        if n > self.count {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Unexpected end of deflate data",
+            ));
+        }
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(())
+    }
+
+    fn bits(&mut self, input: &mut dyn BufRead, n: u32) -> io::Result<u32> {
+        let value = self.peek(input, n)?;
+        self.consume(n)?;
+        Ok(value)
+    }
+
+    /// Offset of the next bit in the input.
+    fn position(&self) -> u64 {
+        self.bytes_read * 8 - self.count as u64
+    }
+}
+
+/// Canonical Huffman code, decoded with a table indexed by the next `bits` bits.
+struct Huffman {
+    /// Symbol << 4 | code length, or 0 for bit patterns without a code
+    table: Vec<u16>,
+    bits: u32,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Huffman> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Lengths that are left over make the code incomplete, which is allowed but leaves bit
+        // patterns without a code. Too many codes are an error.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(invalid_data("Oversubscribed deflate code"));
+            }
+        }
+
+        let bits = (1..16)
+            .rev()
+            .find(|&length| counts[length] > 0)
+            .unwrap_or(0) as u32;
+        let mut next_code = [0u16; 16];
+        for length in 1..16 {
+            next_code[length] = (next_code[length - 1] + counts[length - 1]) << 1;
+        }
+
+        let mut table = vec![0u16; 1 << bits];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length == 0 {
+                continue;
+            }
+            let length = length as u32;
+            let code = next_code[length as usize];
+            next_code[length as usize] += 1;
+            let reversed = (code.reverse_bits() >> (16 - length)) as usize;
+            let entry = (symbol as u16) << 4 | length as u16;
+            for index in (reversed..table.len()).step_by(1 << length) {
+                table[index] = entry;
+            }
+        }
+        Ok(Huffman { table, bits })
+    }
+
+    fn decode(&self, bits: &mut Bits, input: &mut dyn BufRead) -> io::Result<u16> {
+        let entry = self.table[bits.peek(input, self.bits)? as usize];
+        if entry == 0 {
+            return Err(invalid_data("Invalid deflate code"));
+        }
+        bits.consume((entry & 0xf) as u32)?;
+        Ok(entry >> 4)
+    }
+}
+
+enum State {
+    /// At the start of a block
+    Header,
+    Stored {
+        remaining: u16,
+    },
+    Huffman {
+        literals: Huffman,
+        distances: Huffman,
+    },
+    /// The last block ended
+    Done,
+}
+
+/// Raw deflate decoder
+pub(super) struct Inflater {
+    bits: Bits,
+    state: State,
+    last_block: bool,
+    /// The last `WINDOW_SIZE` bytes of output, indexed by their offset modulo `WINDOW_SIZE`
+    window: Vec<u8>,
+    total_out: u64,
+    match_length: usize,
+    match_distance: usize,
+    /// Bits to skip at the start of the input when resuming in the middle of a byte
+    skip_bits: u32,
+}
+
+impl Inflater {
+    /// Resume decoding at a block that starts at `bit_offset` in the compressed data and
+    /// `total_out` in the decompressed data, preceded by `window`.
+    ///
+    /// The input must start at the byte that contains the bit.
+    pub fn resume(bit_offset: u64, total_out: u64, window: &[u8]) -> Inflater {
+        let mut inflater = Inflater {
+            bits: Bits {
+                buffer: 0,
+                count: 0,
+                bytes_read: bit_offset / 8,
+            },
+            state: State::Header,
+            last_block: false,
+            window: vec![0; WINDOW_SIZE],
+            total_out: total_out - window.len() as u64,
+            match_length: 0,
+            match_distance: 0,
+            skip_bits: (bit_offset % 8) as u32,
+        };
+        for &byte in window {
+            inflater.push(byte);
+        }
+        inflater
+    }
+
+    /// Returns the bit offset in the compressed data if decoding is at the start of a block.
+    pub fn block_start(&self) -> Option<u64> {
+        match self.state {
+            State::Header if !self.last_block && self.skip_bits == 0 => Some(self.bits.position()),
+            _ => None,
+        }
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    /// The last bytes of output, up to `WINDOW_SIZE`, in order.
+    pub fn window(&self) -> Vec<u8> {
+        let len = (self.total_out as usize).min(WINDOW_SIZE);
+        let start = self.total_out as usize - len;
+        (start..start + len)
+            .map(|offset| self.window[offset & WINDOW_MASK])
+            .collect()
+    }
+
+    fn push(&mut self, byte: u8) {
+        self.window[self.total_out as usize & WINDOW_MASK] = byte;
+        self.total_out += 1;
+    }
+
+    /// Decompress into `buf`. This stops early at the start of a block, so that the state can
+    /// be saved there.
+    pub fn read(&mut self, input: &mut dyn BufRead, buf: &mut [u8]) -> io::Result<usize> {
+        if self.skip_bits > 0 {
+            self.bits.bits(input, self.skip_bits)?;
+            self.skip_bits = 0;
+        }
+
+        let mut n = 0;
+        while n < buf.len() {
+            if self.match_length > 0 {
+                let byte =
+                    self.window[(self.total_out as usize - self.match_distance) & WINDOW_MASK];
+                self.push(byte);
+                buf[n] = byte;
+                n += 1;
+                self.match_length -= 1;
+                continue;
+            }
+
+            let next_state = match &mut self.state {
+                State::Done => break,
+                State::Header if n > 0 => break,
+                State::Header if self.last_block => State::Done,
+                State::Header => self.read_header(input)?,
+                State::Stored { remaining: 0 } => State::Header,
+                State::Stored { remaining } => {
+                    *remaining -= 1;
+                    let byte = self.bits.bits(input, 8)? as u8;
+                    self.push(byte);
+                    buf[n] = byte;
+                    n += 1;
+                    continue;
+                }
+                State::Huffman {
+                    literals,
+                    distances,
+                } => {
+                    let symbol = literals.decode(&mut self.bits, input)? as usize;
+                    if symbol < 256 {
+                        self.window[self.total_out as usize & WINDOW_MASK] = symbol as u8;
+                        self.total_out += 1;
+                        buf[n] = symbol as u8;
+                        n += 1;
+                        continue;
+                    }
+                    if symbol == 256 {
+                        State::Header
+                    } else {
+                        let symbol = symbol - 257;
+                        if symbol >= LENGTH_BASE.len() {
+                            return Err(invalid_data("Invalid deflate length code"));
+                        }
+                        let length = LENGTH_BASE[symbol] as usize
+                            + self.bits.bits(input, LENGTH_EXTRA[symbol] as u32)? as usize;
+                        let symbol = distances.decode(&mut self.bits, input)? as usize;
+                        if symbol >= DISTANCE_BASE.len() {
+                            return Err(invalid_data("Invalid deflate distance code"));
+                        }
+                        let distance = DISTANCE_BASE[symbol] as usize
+                            + self.bits.bits(input, DISTANCE_EXTRA[symbol] as u32)? as usize;
+                        if distance as u64 > self.total_out {
+                            return Err(invalid_data("Distance too far back in deflate data"));
+                        }
+                        self.match_length = length;
+                        self.match_distance = distance;
+                        continue;
+                    }
+                }
+            };
+            self.state = next_state;
+        }
+        Ok(n)
+    }
+
+    fn read_header(&mut self, input: &mut dyn BufRead) -> io::Result<State> {
+        self.last_block = self.bits.bits(input, 1)? == 1;
+        match self.bits.bits(input, 2)? {
+            0 => {
+                // Stored blocks start at a byte boundary
+                let padding = self.bits.count % 8;
+                self.bits.consume(padding)?;
+                let length = self.bits.bits(input, 16)? as u16;
+                let complement = self.bits.bits(input, 16)? as u16;
+                if length != !complement {
+                    return Err(invalid_data("Invalid stored block length in deflate data"));
+                }
+                Ok(State::Stored { remaining: length })
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].iter_mut().for_each(|length| *length = 8);
+                lengths[144..256].iter_mut().for_each(|length| *length = 9);
+                lengths[256..280].iter_mut().for_each(|length| *length = 7);
+                lengths[280..].iter_mut().for_each(|length| *length = 8);
+                Ok(State::Huffman {
+                    literals: Huffman::new(&lengths)?,
+                    distances: Huffman::new(&[5; 30])?,
+                })
+            }
+            2 => self.read_dynamic_header(input),
+            _ => Err(invalid_data("Invalid deflate block type")),
+        }
+    }
+
+    fn read_dynamic_header(&mut self, input: &mut dyn BufRead) -> io::Result<State> {
+        let literal_count = self.bits.bits(input, 5)? as usize + 257;
+        let distance_count = self.bits.bits(input, 5)? as usize + 1;
+        let code_length_count = self.bits.bits(input, 4)? as usize + 4;
+
+        let mut code_length_lengths = [0u8; 19];
+        for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_length_lengths[symbol] = self.bits.bits(input, 3)? as u8;
+        }
+        let code_lengths = Huffman::new(&code_length_lengths)?;
+
+        let mut lengths = vec![0u8; literal_count + distance_count];
+        let mut i = 0;
+        while i < lengths.len() {
+            let symbol = code_lengths.decode(&mut self.bits, input)?;
+            let (length, repeat) = match symbol {
+                0..=15 => (symbol as u8, 1),
+                16 => match i.checked_sub(1) {
+                    Some(previous) => (lengths[previous], 3 + self.bits.bits(input, 2)? as usize),
+                    None => {
+                        return Err(invalid_data("Repeated code length without a previous one"))
+                    }
+                },
+                17 => (0, 3 + self.bits.bits(input, 3)? as usize),
+                _ => (0, 11 + self.bits.bits(input, 7)? as usize),
+            };
+            if i + repeat > lengths.len() {
+                return Err(invalid_data("Too many code lengths in deflate data"));
+            }
+            lengths[i..i + repeat]
+                .iter_mut()
+                .for_each(|slot| *slot = length);
+            i += repeat;
+        }
+        if lengths[256] == 0 {
+            return Err(invalid_data("Missing end of block code in deflate data"));
+        }
+
+        Ok(State::Huffman {
+            literals: Huffman::new(&lengths[..literal_count])?,
+            distances: Huffman::new(&lengths[literal_count..])?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Inflater;
+    use flate2::write::DeflateEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn sample() -> Vec<u8> {
+        // Text with repetitions at all distances, followed by incompressible bytes
+        let mut data = Vec::new();
+        let mut state = 1u32;
+        for i in 0..60000 {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            let word =
+                ["zip", "deflate", "block", "window", "checkpoint"][(state >> 16) as usize % 5];
+            data.extend_from_slice(word.as_bytes());
+            data.push(if i % 13 == 0 { b'\n' } else { b' ' });
+        }
+        for _ in 0..100000 {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            data.push((state >> 16) as u8);
+        }
+        data
+    }
+
+    fn inflate(inflater: &mut Inflater, mut compressed: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let mut buf = [0; 1000];
+        loop {
+            let count = inflater.read(&mut compressed, &mut buf).unwrap();
+            if count == 0 {
+                return output;
+            }
+            output.extend_from_slice(&buf[..count]);
+        }
+    }
+
+    #[test]
+    fn matches_flate2() {
+        let data = sample();
+        for level in [0, 1, 6, 9] {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::new(level));
+            encoder.write_all(&data).unwrap();
+            let compressed = encoder.finish().unwrap();
+            let output = inflate(&mut Inflater::resume(0, 0, &[]), &compressed);
+            assert!(output == data, "level {}", level);
+        }
+    }
+
+    #[test]
+    fn resume_at_block() {
+        let data = sample();
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut inflater = Inflater::resume(0, 0, &[]);
+        let mut input = &compressed[..];
+        let mut buf = [0; 1000];
+        let mut blocks = Vec::new();
+        loop {
+            if let Some(offset) = inflater.block_start() {
+                blocks.push((offset, inflater.total_out(), inflater.window()));
+            }
+            if inflater.read(&mut input, &mut buf).unwrap() == 0 {
+                break;
+            }
+        }
+        assert!(blocks.len() > 2, "{}", blocks.len());
+
+        for (offset, total_out, window) in blocks {
+            let mut inflater = Inflater::resume(offset, total_out, &window);
+            let output = inflate(&mut inflater, &compressed[offset as usize / 8..]);
+            assert!(output == data[total_out as usize..]);
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let data = sample();
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&data).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let mut inflater = Inflater::resume(0, 0, &[]);
+        let mut input = &compressed[..compressed.len() / 2];
+        let mut buf = [0; 1000];
+        let error = loop {
+            match inflater.read(&mut input, &mut buf) {
+                Ok(0) => panic!("truncated data was decoded"),
+                Ok(_) => {}
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(error.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
+}
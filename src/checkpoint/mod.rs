@@ -0,0 +1,531 @@
+//! Random access into compressed files through checkpoints of the decoder state.
+//!
+//! A [`CheckpointIndex`] records where decoding can be resumed in a compressed file: the start
+//! of deflate blocks, along with the 32 KiB of output before them, and the start of zstd frames.
+
+use crate::compression::CompressionMethod;
+use crate::read::seek_position;
+use crate::result::{ZipError, ZipResult};
+use crate::types::ZipFileData;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, prelude::*};
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+mod inflate;
+#[cfg(feature = "zstd")]
+mod zstd_frames;
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+use inflate::Inflater;
+#[cfg(feature = "zstd")]
+use zstd_frames::ZstdFrames;
+
+#[cfg(feature = "zstd")]
+use crate::read::ZstdDictionaries;
+
+const INDEX_SIGNATURE: u32 = 0x54504b43;
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// A place where decoding can resume
+#[derive(Clone, Debug)]
+struct Checkpoint {
+    uncompressed_offset: u64,
+    /// Offset in the compressed data, in bits
+    compressed_offset: u64,
+    /// The output that precedes the checkpoint and can be referenced after it
+    window: Vec<u8>,
+}
+
+/// Checkpoints of the decoder state in a compressed file, for random access
+///
+/// An index is built by [`ZipArchive::build_checkpoint_index`](crate::read::ZipArchive::build_checkpoint_index),
+/// which decompresses the file once, and used by
+/// [`ZipArchive::by_index_with_checkpoints`](crate::read::ZipArchive::by_index_with_checkpoints).
+/// It can be saved with [`CheckpointIndex::write`] and loaded again with
+/// [`CheckpointIndex::parse`], to avoid decompressing the file again.
+///
+/// Deflate files can be resumed at the start of any deflate block, and each checkpoint stores
+/// up to 32 KiB of decompressed data. Zstd files can only be resumed at the start of a frame, so
+/// files compressed as a single frame have a checkpoint at their start only. This includes all
+/// zstd files written by [`ZipWriter`](crate::ZipWriter), so random access only helps zstd files
+/// that another tool compressed as multiple frames, such as `pzstd` or the zstd seekable format.
+#[derive(Clone, Debug)]
+pub struct CheckpointIndex {
+    compression_method: CompressionMethod,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    checkpoints: Vec<Checkpoint>,
+}
+
+impl CheckpointIndex {
+    /// Number of checkpoints, including the one at the start of the file
+    pub fn len(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    /// Whether the index has no checkpoints, which is never the case for a valid index
+    pub fn is_empty(&self) -> bool {
+        self.checkpoints.is_empty()
+    }
+
+    /// Read an index written by [`CheckpointIndex::write`].
+    pub fn parse<R: Read>(reader: &mut R) -> ZipResult<CheckpointIndex> {
+        if reader.read_u32::<LittleEndian>()? != INDEX_SIGNATURE {
+            return Err(ZipError::InvalidArchive(
+                "Invalid checkpoint index signature",
+            ));
+        }
+        let compression_method = {
+            #[allow(deprecated)]
+            CompressionMethod::from_u16(reader.read_u16::<LittleEndian>()?)
+        };
+        let crc32 = reader.read_u32::<LittleEndian>()?;
+        let compressed_size = reader.read_u64::<LittleEndian>()?;
+        let uncompressed_size = reader.read_u64::<LittleEndian>()?;
+        let count = reader.read_u32::<LittleEndian>()?;
+
+        let mut checkpoints = Vec::new();
+        for _ in 0..count {
+            let uncompressed_offset = reader.read_u64::<LittleEndian>()?;
+            let compressed_offset = reader.read_u64::<LittleEndian>()?;
+            let window_len = reader.read_u32::<LittleEndian>()? as usize;
+            if window_len > 32768
+                || window_len as u64 > uncompressed_offset
+                || compressed_offset / 8 > compressed_size
+                || uncompressed_offset > uncompressed_size
+            {
+                return Err(ZipError::InvalidArchive("Invalid checkpoint"));
+            }
+            let mut window = vec![0; window_len];
+            reader.read_exact(&mut window)?;
+            checkpoints.push(Checkpoint {
+                uncompressed_offset,
+                compressed_offset,
+                window,
+            });
+        }
+        if checkpoints.first().map(|c| c.uncompressed_offset) != Some(0)
+            || checkpoints
+                .windows(2)
+                .any(|pair| pair[0].uncompressed_offset > pair[1].uncompressed_offset)
+        {
+            return Err(ZipError::InvalidArchive("Invalid checkpoint index"));
+        }
+
+        Ok(CheckpointIndex {
+            compression_method,
+            crc32,
+            compressed_size,
+            uncompressed_size,
+            checkpoints,
+        })
+    }
+
+    /// Write the index, to be read again by [`CheckpointIndex::parse`].
+    pub fn write<W: Write>(&self, writer: &mut W) -> ZipResult<()> {
+        writer.write_u32::<LittleEndian>(INDEX_SIGNATURE)?;
+        #[allow(deprecated)]
+        writer.write_u16::<LittleEndian>(self.compression_method.to_u16())?;
+        writer.write_u32::<LittleEndian>(self.crc32)?;
+        writer.write_u64::<LittleEndian>(self.compressed_size)?;
+        writer.write_u64::<LittleEndian>(self.uncompressed_size)?;
+        writer.write_u32::<LittleEndian>(self.checkpoints.len() as u32)?;
+        for checkpoint in &self.checkpoints {
+            writer.write_u64::<LittleEndian>(checkpoint.uncompressed_offset)?;
+            writer.write_u64::<LittleEndian>(checkpoint.compressed_offset)?;
+            writer.write_u32::<LittleEndian>(checkpoint.window.len() as u32)?;
+            writer.write_all(&checkpoint.window)?;
+        }
+        Ok(())
+    }
+
+    /// Decompress a file, recording a checkpoint whenever at least `spacing` bytes were
+    /// decompressed since the last one.
+    pub(crate) fn build<R: Read + Seek>(
+        data: &ZipFileData,
+        reader: &mut R,
+        spacing: u64,
+        #[cfg(feature = "zstd")] zstd_dictionaries: Option<&ZstdDictionaries>,
+    ) -> ZipResult<CheckpointIndex> {
+        let mut input = Input::new(reader, data.data_start.load(), data.compressed_size);
+        let start = Checkpoint {
+            uncompressed_offset: 0,
+            compressed_offset: 0,
+            window: Vec::new(),
+        };
+        let mut engine = Engine::resume(
+            data.compression_method,
+            &start,
+            &mut input,
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries,
+        )?;
+
+        let mut checkpoints = vec![start];
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buf = vec![0; 1 << 16];
+        loop {
+            let last = checkpoints.last().unwrap().uncompressed_offset;
+            if engine.total_out() - last >= spacing.max(1)
+                && engine.total_out() < data.uncompressed_size
+            {
+                if let Some(checkpoint) = engine.checkpoint() {
+                    checkpoints.push(checkpoint);
+                }
+            }
+            let count = engine.read(&mut input, &mut buf)?;
+            if count == 0 {
+                break;
+            }
+            hasher.update(&buf[..count]);
+        }
+        if engine.total_out() != data.uncompressed_size || hasher.finalize() != data.crc32 {
+            return Err(invalid_data("Invalid checksum").into());
+        }
+
+        Ok(CheckpointIndex {
+            compression_method: data.compression_method,
+            crc32: data.crc32,
+            compressed_size: data.compressed_size,
+            uncompressed_size: data.uncompressed_size,
+            checkpoints,
+        })
+    }
+
+    /// Check that this index was built for the file `data`.
+    pub(crate) fn check(&self, data: &ZipFileData) -> ZipResult<()> {
+        if self.compression_method != data.compression_method
+            || self.crc32 != data.crc32
+            || self.compressed_size != data.compressed_size
+            || self.uncompressed_size != data.uncompressed_size
+        {
+            return Err(ZipError::InvalidArchive(
+                "Checkpoint index doesn't match the file",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The last checkpoint at or before `offset` in the decompressed data
+    fn checkpoint_before(&self, offset: u64) -> &Checkpoint {
+        let index = self
+            .checkpoints
+            .partition_point(|checkpoint| checkpoint.uncompressed_offset <= offset);
+        &self.checkpoints[index.saturating_sub(1)]
+    }
+}
+
+/// Check that a file can be read with checkpoints.
+pub(crate) fn check_supported(data: &ZipFileData) -> ZipResult<()> {
+    if data.encrypted {
+        return Err(ZipError::UnsupportedArchive(
+            "Random access is not supported for encrypted files",
+        ));
+    }
+    #[allow(deprecated)]
+    match data.compression_method {
+        #[cfg(any(
+            feature = "deflate",
+            feature = "deflate-miniz",
+            feature = "deflate-zlib"
+        ))]
+        CompressionMethod::Deflated => Ok(()),
+        #[cfg(feature = "zstd")]
+        CompressionMethod::Zstd => Ok(()),
+        _ => Err(ZipError::UnsupportedArchive(
+            "Random access is only supported for deflate and zstd files",
+        )),
+    }
+}
+
+/// Buffered reader over the compressed data of a file, which can be moved to another offset.
+struct Input<R> {
+    reader: R,
+    data_start: u64,
+    compressed_size: u64,
+    buffer: Box<[u8]>,
+    pos: usize,
+    filled: usize,
+    remaining: u64,
+}
+
+impl<R: Read + Seek> Input<R> {
+    fn new(reader: R, data_start: u64, compressed_size: u64) -> Input<R> {
+        Input {
+            reader,
+            data_start,
+            compressed_size,
+            buffer: vec![0; 1 << 16].into_boxed_slice(),
+            pos: 0,
+            filled: 0,
+            // Seek before the first read
+            remaining: u64::MAX,
+        }
+    }
+
+    fn seek_to(&mut self, offset: u64) -> io::Result<()> {
+        self.reader
+            .seek(io::SeekFrom::Start(self.data_start + offset))?;
+        self.pos = 0;
+        self.filled = 0;
+        self.remaining = self.compressed_size.saturating_sub(offset);
+        Ok(())
+    }
+}
+
+impl<R: Read + Seek> Read for Input<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.fill_buf()?;
+        let count = data.len().min(buf.len());
+        buf[..count].copy_from_slice(&data[..count]);
+        self.consume(count);
+        Ok(count)
+    }
+}
+
+impl<R: Read + Seek> BufRead for Input<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.filled && self.remaining > 0 {
+            let len = (self.buffer.len() as u64).min(self.remaining) as usize;
+            let count = self.reader.read(&mut self.buffer[..len])?;
+            self.pos = 0;
+            self.filled = count;
+            self.remaining -= count as u64;
+            if count == 0 {
+                self.remaining = 0;
+            }
+        }
+        Ok(&self.buffer[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+/// A decoder that can be resumed at checkpoints
+enum Engine {
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    Deflate(Inflater),
+    #[cfg(feature = "zstd")]
+    Zstd(ZstdFrames),
+}
+
+impl Engine {
+    /// Seek `input` to `checkpoint` and create a decoder from there.
+    fn resume<R: Read + Seek>(
+        compression_method: CompressionMethod,
+        checkpoint: &Checkpoint,
+        input: &mut Input<R>,
+        #[cfg(feature = "zstd")] zstd_dictionaries: Option<&ZstdDictionaries>,
+    ) -> io::Result<Engine> {
+        input.seek_to(checkpoint.compressed_offset / 8)?;
+        #[allow(deprecated)]
+        match compression_method {
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            CompressionMethod::Deflated => Ok(Engine::Deflate(Inflater::resume(
+                checkpoint.compressed_offset,
+                checkpoint.uncompressed_offset,
+                &checkpoint.window,
+            ))),
+            #[cfg(feature = "zstd")]
+            CompressionMethod::Zstd => {
+                // Peek at the frame header to find the dictionary it was compressed with.
+                // Errors will surface again once the decoder reads from `input`.
+                let dictionary = match zstd_dictionaries {
+                    Some(dictionaries) => input
+                        .fill_buf()
+                        .ok()
+                        .and_then(|data| dictionaries.for_frame(data))
+                        .cloned(),
+                    None => None,
+                };
+                Ok(Engine::Zstd(ZstdFrames::resume(
+                    checkpoint.compressed_offset / 8,
+                    checkpoint.uncompressed_offset,
+                    dictionary,
+                )?))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "Random access is only supported for deflate and zstd files",
+            )),
+        }
+    }
+
+    // Without deflate and zstd support there are no engines
+    #[allow(unused_variables)]
+    fn read(&mut self, input: &mut dyn BufRead, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            Engine::Deflate(ref mut inflater) => inflater.read(input, buf),
+            #[cfg(feature = "zstd")]
+            Engine::Zstd(ref mut frames) => frames.read(input, buf),
+        }
+    }
+
+    fn total_out(&self) -> u64 {
+        match *self {
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            Engine::Deflate(ref inflater) => inflater.total_out(),
+            #[cfg(feature = "zstd")]
+            Engine::Zstd(ref frames) => frames.total_out(),
+        }
+    }
+
+    /// The current state, if decoding can be resumed from it
+    fn checkpoint(&self) -> Option<Checkpoint> {
+        match *self {
+            #[cfg(any(
+                feature = "deflate",
+                feature = "deflate-miniz",
+                feature = "deflate-zlib"
+            ))]
+            Engine::Deflate(ref inflater) => inflater.block_start().map(|offset| Checkpoint {
+                uncompressed_offset: inflater.total_out(),
+                compressed_offset: offset,
+                window: inflater.window(),
+            }),
+            #[cfg(feature = "zstd")]
+            Engine::Zstd(ref frames) => frames.frame_start().map(|offset| Checkpoint {
+                uncompressed_offset: frames.total_out(),
+                compressed_offset: offset * 8,
+                window: Vec::new(),
+            }),
+        }
+    }
+}
+
+/// A seekable reader for a compressed file in a zip archive, which resumes decompressing from
+/// the checkpoint before the position it reads from
+///
+/// Created by [`ZipArchive::by_index_with_checkpoints`](crate::read::ZipArchive::by_index_with_checkpoints).
+/// The CRC-32 of the file isn't checked, as the file may be read out of order or only in part.
+pub struct CheckpointedZipFile<'a, R> {
+    input: Input<&'a mut R>,
+    data: &'a ZipFileData,
+    index: &'a CheckpointIndex,
+    #[cfg(feature = "zstd")]
+    zstd_dictionaries: Option<&'a ZstdDictionaries>,
+    engine: Option<Engine>,
+    position: u64,
+    /// Output that is decoded only to be skipped, allocated on the first skip
+    skip: Vec<u8>,
+}
+
+impl<'a, R: Read + Seek> CheckpointedZipFile<'a, R> {
+    pub(crate) fn new(
+        reader: &'a mut R,
+        data: &'a ZipFileData,
+        index: &'a CheckpointIndex,
+        #[cfg(feature = "zstd")] zstd_dictionaries: Option<&'a ZstdDictionaries>,
+    ) -> CheckpointedZipFile<'a, R> {
+        CheckpointedZipFile {
+            input: Input::new(reader, data.data_start.load(), data.compressed_size),
+            data,
+            index,
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries,
+            engine: None,
+            position: 0,
+            skip: Vec::new(),
+        }
+    }
+
+    /// Get the name of the file
+    pub fn name(&self) -> &str {
+        &self.data.file_name
+    }
+
+    /// Get the size of the file, in bytes
+    pub fn size(&self) -> u64 {
+        self.data.uncompressed_size
+    }
+
+    /// Move the decoder to `self.position`, resuming from a checkpoint if that is closer.
+    fn prepare(&mut self) -> io::Result<()> {
+        let index = self.index;
+        let checkpoint = index.checkpoint_before(self.position);
+        let resume = match &self.engine {
+            Some(engine) => {
+                engine.total_out() > self.position
+                    || engine.total_out() < checkpoint.uncompressed_offset
+            }
+            None => true,
+        };
+        if resume {
+            // Don't keep using the old decoder if resuming fails, as the input has moved
+            self.engine = None;
+            self.engine = Some(Engine::resume(
+                self.data.compression_method,
+                checkpoint,
+                &mut self.input,
+                #[cfg(feature = "zstd")]
+                self.zstd_dictionaries,
+            )?);
+        }
+
+        let engine = self.engine.as_mut().unwrap();
+        if engine.total_out() < self.position && self.skip.is_empty() {
+            self.skip = vec![0; 1 << 16];
+        }
+        while engine.total_out() < self.position {
+            let len = (self.position - engine.total_out()).min(self.skip.len() as u64) as usize;
+            if engine.read(&mut self.input, &mut self.skip[..len])? == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, R: Read + Seek> Read for CheckpointedZipFile<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.data.uncompressed_size {
+            return Ok(0);
+        }
+        self.prepare()?;
+        let engine = self.engine.as_mut().unwrap();
+        if engine.total_out() != self.position {
+            return Ok(0);
+        }
+        let count = engine.read(&mut self.input, buf)?;
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<'a, R> Seek for CheckpointedZipFile<'a, R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let size = self.data.uncompressed_size;
+        seek_position(&mut self.position, pos, || Ok(size))
+    }
+}
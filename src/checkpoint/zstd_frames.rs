@@ -0,0 +1,89 @@
+//! A zstd decoder which can be resumed at the start of any frame.
+//!
+//! Frames are independent of each other, so decoding can resume at a frame from its offset in
+//! the compressed data. Data compressed as a single frame can only be decoded from its start.
+
+use std::io::{self, BufRead};
+use std::sync::Arc;
+use zstd::dict::DecoderDictionary;
+use zstd::stream::raw::{Decoder, Operation};
+
+pub(super) struct ZstdFrames {
+    decoder: Decoder<'static>,
+    /// Keeps the dictionary referenced by `decoder` alive
+    _dictionary: Option<Arc<DecoderDictionary<'static>>>,
+    frame_start: bool,
+    bytes_read: u64,
+    total_out: u64,
+}
+
+impl ZstdFrames {
+    /// Resume decoding at a frame that starts at `offset` in the compressed data and
+    /// `total_out` in the decompressed data.
+    pub fn resume(
+        offset: u64,
+        total_out: u64,
+        dictionary: Option<Arc<DecoderDictionary<'static>>>,
+    ) -> io::Result<ZstdFrames> {
+        let decoder = match &dictionary {
+            Some(dictionary) => Decoder::with_prepared_dictionary(dictionary)?,
+            None => Decoder::new()?,
+        };
+        Ok(ZstdFrames {
+            decoder,
+            _dictionary: dictionary,
+            frame_start: true,
+            bytes_read: offset,
+            total_out,
+        })
+    }
+
+    /// Returns the offset in the compressed data if decoding is at the start of a frame.
+    pub fn frame_start(&self) -> Option<u64> {
+        if self.frame_start {
+            Some(self.bytes_read)
+        } else {
+            None
+        }
+    }
+
+    pub fn total_out(&self) -> u64 {
+        self.total_out
+    }
+
+    /// Decompress into `buf`. This stops early at the end of a frame, so that the state can be
+    /// saved there.
+    pub fn read(&mut self, input: &mut dyn BufRead, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let data = input.fill_buf()?;
+            if data.is_empty() && self.frame_start {
+                return Ok(0);
+            }
+            let at_end = data.is_empty();
+            let status = self.decoder.run_on_buffers(data, buf)?;
+            input.consume(status.bytes_read);
+            self.bytes_read += status.bytes_read as u64;
+            self.total_out += status.bytes_written as u64;
+            if status.bytes_read > 0 {
+                self.frame_start = false;
+            }
+            if status.remaining == 0 {
+                // The frame is complete and flushed
+                self.frame_start = true;
+                self.decoder.reinit()?;
+            }
+            if status.bytes_written > 0 {
+                return Ok(status.bytes_written);
+            }
+            if at_end && !self.frame_start {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Unexpected end of zstd data",
+                ));
+            }
+        }
+    }
+}
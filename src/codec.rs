@@ -0,0 +1,217 @@
+//! Custom compression codecs
+//!
+//! A [`Codec`] provides the compression and decompression for a compression method, identified
+//! by the method ID stored in the zip file. Codecs are registered with
+//! [`ZipArchive::register_codec`](crate::read::ZipArchive::register_codec) for reading and with
+//! [`ZipWriter::register_codec`](crate::write::ZipWriter::register_codec) for writing. This allows
+//! using compression methods that this crate doesn't implement, or replacing the implementation
+//! of one that it does. A registered codec takes precedence over the built-in implementation of
+//! its method, except for [`CompressionMethod::Stored`], which is never handled by a codec.
+//!
+//! To write files with a method that has no [`CompressionMethod`] variant, use
+//! [`CompressionMethod::from_u16`] with its ID.
+//!
+//! ```
+//! use std::io::{self, BufRead, Read, Write};
+//! use zip::codec::{Codec, Decoder, Encoder};
+//!
+//! /// A method which inverts every byte
+//! struct Invert;
+//!
+//! impl Codec for Invert {
+//!     fn decoder(&self, _uncompressed_size: u64) -> io::Result<Box<dyn Decoder>> {
+//!         Ok(Box::new(Invert))
+//!     }
+//!
+//!     fn encoder(&self) -> io::Result<Box<dyn Encoder>> {
+//!         Ok(Box::new(Invert))
+//!     }
+//! }
+//!
+//! impl Decoder for Invert {
+//!     fn read(&mut self, input: &mut dyn BufRead, buf: &mut [u8]) -> io::Result<usize> {
+//!         let count = input.read(buf)?;
+//!         buf[..count].iter_mut().for_each(|byte| *byte = !*byte);
+//!         Ok(count)
+//!     }
+//! }
+//!
+//! impl Encoder for Invert {
+//!     fn write(&mut self, input: &[u8], output: &mut dyn Write) -> io::Result<()> {
+//!         let inverted: Vec<u8> = input.iter().map(|byte| !byte).collect();
+//!         output.write_all(&inverted)
+//!     }
+//!
+//!     fn finish(&mut self, _output: &mut dyn Write) -> io::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! # fn main() -> zip::result::ZipResult<()> {
+//! const INVERT: u16 = 0xff01;
+//!
+//! let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+//! zip.register_codec(INVERT, Invert);
+//! let options = zip::write::FileOptions::default()
+//!     .compression_method(zip::CompressionMethod::from_u16(INVERT));
+//! zip.start_file("inverted.txt", options)?;
+//! zip.write_all(b"Hello, World!")?;
+//! let buf = zip.finish()?;
+//!
+//! let mut archive = zip::ZipArchive::new(buf)?;
+//! archive.register_codec(INVERT, Invert);
+//! let mut contents = String::new();
+//! archive.by_name("inverted.txt")?.read_to_string(&mut contents)?;
+//! assert_eq!(contents, "Hello, World!");
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::compression::CompressionMethod;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use std::sync::Arc;
+
+/// A compression method implementation, which creates a decoder or encoder for each file.
+pub trait Codec: Send + Sync {
+    /// Create a decoder for a file with the given uncompressed size.
+    ///
+    /// The default implementation returns an error, for codecs that can only compress.
+    fn decoder(&self, uncompressed_size: u64) -> io::Result<Box<dyn Decoder>> {
+        let _ = uncompressed_size;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Decompression is not supported by this codec",
+        ))
+    }
+
+    /// Create an encoder for a new file.
+    ///
+    /// The default implementation returns an error, for codecs that can only decompress.
+    fn encoder(&self) -> io::Result<Box<dyn Encoder>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Compression is not supported by this codec",
+        ))
+    }
+}
+
+/// Decompresses the data of a single file.
+pub trait Decoder: Send {
+    /// Read decompressed data into `buf`, returning how many bytes were read.
+    ///
+    /// `input` yields the compressed data of the file, already decrypted, and ends with it.
+    /// Returning 0 for a non-empty `buf` marks the end of the decompressed data, after which the
+    /// CRC-32 of the file is checked.
+    fn read(&mut self, input: &mut dyn BufRead, buf: &mut [u8]) -> io::Result<usize>;
+}
+
+/// Compresses the data of a single file.
+pub trait Encoder: Send {
+    /// Compress all of `input`, writing compressed data to `output` as it becomes available.
+    fn write(&mut self, input: &[u8], output: &mut dyn Write) -> io::Result<()>;
+
+    /// Write the remaining compressed data to `output`. This is called once, after the last call
+    /// to [`Encoder::write`].
+    fn finish(&mut self, output: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Registered codecs, keyed by their method ID
+#[derive(Clone, Default)]
+pub(crate) struct Codecs(HashMap<u16, Arc<dyn Codec>>);
+
+impl Codecs {
+    pub fn insert(&mut self, method: u16, codec: Arc<dyn Codec>) {
+        self.0.insert(method, codec);
+    }
+
+    /// Find the codec for `method`. Stored files are never handled by a codec.
+    pub fn get(&self, method: CompressionMethod) -> Option<&Arc<dyn Codec>> {
+        if method == CompressionMethod::Stored {
+            return None;
+        }
+        #[allow(deprecated)]
+        self.0.get(&method.to_u16())
+    }
+}
+
+impl std::fmt::Debug for Codecs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// Reader for data decompressed by a codec.
+///
+/// The decoder is created on the first read, so that errors creating it are reported by reading.
+pub(crate) struct CodecReader<R: Read> {
+    inner: io::BufReader<R>,
+    codec: Arc<dyn Codec>,
+    uncompressed_size: u64,
+    decoder: Option<Box<dyn Decoder>>,
+}
+
+impl<R: Read> CodecReader<R> {
+    pub fn new(inner: R, codec: Arc<dyn Codec>, uncompressed_size: u64) -> CodecReader<R> {
+        CodecReader {
+            inner: io::BufReader::new(inner),
+            codec,
+            uncompressed_size,
+            decoder: None,
+        }
+    }
+
+    /// Consumes this decoder, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+}
+
+impl<R: Read> Read for CodecReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let decoder = match &mut self.decoder {
+            Some(decoder) => decoder,
+            None => self
+                .decoder
+                .insert(self.codec.decoder(self.uncompressed_size)?),
+        };
+        decoder.read(&mut self.inner, buf)
+    }
+}
+
+/// Writer for data compressed by a codec.
+pub(crate) struct CodecWriter<W: Write> {
+    inner: W,
+    encoder: Box<dyn Encoder>,
+    method: CompressionMethod,
+}
+
+impl<W: Write> CodecWriter<W> {
+    pub fn new(inner: W, codec: &dyn Codec, method: CompressionMethod) -> io::Result<Self> {
+        Ok(CodecWriter {
+            inner,
+            encoder: codec.encoder()?,
+            method,
+        })
+    }
+
+    pub fn method(&self) -> CompressionMethod {
+        self.method
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.encoder.finish(&mut self.inner)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for CodecWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encoder.write(buf, &mut self.inner)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -14,6 +14,30 @@ use std::fmt;
 pub enum CompressionMethod {
     /// Store the file as is
     Stored,
+    /// Compress the file using Shrink.
+    /// Only decompression is supported.
+    #[cfg(feature = "legacy")]
+    Shrink,
+    /// Compress the file using Reduce with compression factor 1.
+    /// Only decompression is supported.
+    #[cfg(feature = "legacy")]
+    Reduce1,
+    /// Compress the file using Reduce with compression factor 2.
+    /// Only decompression is supported.
+    #[cfg(feature = "legacy")]
+    Reduce2,
+    /// Compress the file using Reduce with compression factor 3.
+    /// Only decompression is supported.
+    #[cfg(feature = "legacy")]
+    Reduce3,
+    /// Compress the file using Reduce with compression factor 4.
+    /// Only decompression is supported.
+    #[cfg(feature = "legacy")]
+    Reduce4,
+    /// Compress the file using Implode.
+    /// Only decompression is supported.
+    #[cfg(feature = "legacy")]
+    Implode,
     /// Compress the file using Deflate
     #[cfg(any(
         feature = "deflate",
@@ -21,12 +45,29 @@ pub enum CompressionMethod {
         feature = "deflate-zlib"
     ))]
     Deflated,
+    /// Compress the file using Deflate64.
+    /// Decoding deflate64 is supported but encoding deflate64 is not supported.
+    #[cfg(feature = "deflate64")]
+    Deflate64,
+    /// Compress the file using PKWARE Data Compression Library Implode.
+    /// Only decompression is supported.
+    #[cfg(feature = "legacy")]
+    PkwareImplode,
     /// Compress the file using BZIP2
     #[cfg(feature = "bzip2")]
     Bzip2,
+    /// Compress the file using LZMA
+    #[cfg(feature = "lzma")]
+    Lzma,
     /// Compress the file using ZStandard
     #[cfg(feature = "zstd")]
     Zstd,
+    /// Compress the file using XZ
+    #[cfg(feature = "xz")]
+    Xz,
+    /// Compress the file using PPMd
+    #[cfg(feature = "ppmd")]
+    Ppmd,
     /// Unsupported compression method
     #[deprecated(since = "0.5.7", note = "use the constants instead")]
     Unsupported(u16),
@@ -35,11 +76,29 @@ pub enum CompressionMethod {
 /// All compression methods defined for the ZIP format
 impl CompressionMethod {
     pub const STORE: Self = CompressionMethod::Stored;
+    #[cfg(feature = "legacy")]
+    pub const SHRINK: Self = CompressionMethod::Shrink;
+    #[cfg(not(feature = "legacy"))]
     pub const SHRINK: Self = CompressionMethod::Unsupported(1);
+    #[cfg(feature = "legacy")]
+    pub const REDUCE_1: Self = CompressionMethod::Reduce1;
+    #[cfg(not(feature = "legacy"))]
     pub const REDUCE_1: Self = CompressionMethod::Unsupported(2);
+    #[cfg(feature = "legacy")]
+    pub const REDUCE_2: Self = CompressionMethod::Reduce2;
+    #[cfg(not(feature = "legacy"))]
     pub const REDUCE_2: Self = CompressionMethod::Unsupported(3);
+    #[cfg(feature = "legacy")]
+    pub const REDUCE_3: Self = CompressionMethod::Reduce3;
+    #[cfg(not(feature = "legacy"))]
     pub const REDUCE_3: Self = CompressionMethod::Unsupported(4);
+    #[cfg(feature = "legacy")]
+    pub const REDUCE_4: Self = CompressionMethod::Reduce4;
+    #[cfg(not(feature = "legacy"))]
     pub const REDUCE_4: Self = CompressionMethod::Unsupported(5);
+    #[cfg(feature = "legacy")]
+    pub const IMPLODE: Self = CompressionMethod::Implode;
+    #[cfg(not(feature = "legacy"))]
     pub const IMPLODE: Self = CompressionMethod::Unsupported(6);
     #[cfg(any(
         feature = "deflate",
@@ -53,12 +112,21 @@ impl CompressionMethod {
         feature = "deflate-zlib"
     )))]
     pub const DEFLATE: Self = CompressionMethod::Unsupported(8);
+    #[cfg(feature = "deflate64")]
+    pub const DEFLATE64: Self = CompressionMethod::Deflate64;
+    #[cfg(not(feature = "deflate64"))]
     pub const DEFLATE64: Self = CompressionMethod::Unsupported(9);
+    #[cfg(feature = "legacy")]
+    pub const PKWARE_IMPLODE: Self = CompressionMethod::PkwareImplode;
+    #[cfg(not(feature = "legacy"))]
     pub const PKWARE_IMPLODE: Self = CompressionMethod::Unsupported(10);
     #[cfg(feature = "bzip2")]
     pub const BZIP2: Self = CompressionMethod::Bzip2;
     #[cfg(not(feature = "bzip2"))]
     pub const BZIP2: Self = CompressionMethod::Unsupported(12);
+    #[cfg(feature = "lzma")]
+    pub const LZMA: Self = CompressionMethod::Lzma;
+    #[cfg(not(feature = "lzma"))]
     pub const LZMA: Self = CompressionMethod::Unsupported(14);
     pub const IBM_ZOS_CMPSC: Self = CompressionMethod::Unsupported(16);
     pub const IBM_TERSE: Self = CompressionMethod::Unsupported(18);
@@ -68,9 +136,15 @@ impl CompressionMethod {
     #[cfg(not(feature = "zstd"))]
     pub const ZSTD: Self = CompressionMethod::Unsupported(93);
     pub const MP3: Self = CompressionMethod::Unsupported(94);
+    #[cfg(feature = "xz")]
+    pub const XZ: Self = CompressionMethod::Xz;
+    #[cfg(not(feature = "xz"))]
     pub const XZ: Self = CompressionMethod::Unsupported(95);
     pub const JPEG: Self = CompressionMethod::Unsupported(96);
     pub const WAVPACK: Self = CompressionMethod::Unsupported(97);
+    #[cfg(feature = "ppmd")]
+    pub const PPMD: Self = CompressionMethod::Ppmd;
+    #[cfg(not(feature = "ppmd"))]
     pub const PPMD: Self = CompressionMethod::Unsupported(98);
     /// Encrypted using AES.
     ///
@@ -80,24 +154,45 @@ impl CompressionMethod {
 }
 impl CompressionMethod {
     /// Converts an u16 to its corresponding CompressionMethod
-    #[deprecated(
-        since = "0.5.7",
-        note = "use a constant to construct a compression method"
-    )]
+    ///
+    /// Use the constants for the methods defined by the ZIP format. This is meant for methods
+    /// handled by a custom [`Codec`](crate::codec::Codec), which have no constant.
     pub fn from_u16(val: u16) -> CompressionMethod {
         #[allow(deprecated)]
         match val {
             0 => CompressionMethod::Stored,
+            #[cfg(feature = "legacy")]
+            1 => CompressionMethod::Shrink,
+            #[cfg(feature = "legacy")]
+            2 => CompressionMethod::Reduce1,
+            #[cfg(feature = "legacy")]
+            3 => CompressionMethod::Reduce2,
+            #[cfg(feature = "legacy")]
+            4 => CompressionMethod::Reduce3,
+            #[cfg(feature = "legacy")]
+            5 => CompressionMethod::Reduce4,
+            #[cfg(feature = "legacy")]
+            6 => CompressionMethod::Implode,
             #[cfg(any(
                 feature = "deflate",
                 feature = "deflate-miniz",
                 feature = "deflate-zlib"
             ))]
             8 => CompressionMethod::Deflated,
+            #[cfg(feature = "deflate64")]
+            9 => CompressionMethod::Deflate64,
+            #[cfg(feature = "legacy")]
+            10 => CompressionMethod::PkwareImplode,
             #[cfg(feature = "bzip2")]
             12 => CompressionMethod::Bzip2,
+            #[cfg(feature = "lzma")]
+            14 => CompressionMethod::Lzma,
             #[cfg(feature = "zstd")]
             93 => CompressionMethod::Zstd,
+            #[cfg(feature = "xz")]
+            95 => CompressionMethod::Xz,
+            #[cfg(feature = "ppmd")]
+            98 => CompressionMethod::Ppmd,
             99 => CompressionMethod::AES,
 
             v => CompressionMethod::Unsupported(v),
@@ -113,17 +208,39 @@ impl CompressionMethod {
         #[allow(deprecated)]
         match self {
             CompressionMethod::Stored => 0,
+            #[cfg(feature = "legacy")]
+            CompressionMethod::Shrink => 1,
+            #[cfg(feature = "legacy")]
+            CompressionMethod::Reduce1 => 2,
+            #[cfg(feature = "legacy")]
+            CompressionMethod::Reduce2 => 3,
+            #[cfg(feature = "legacy")]
+            CompressionMethod::Reduce3 => 4,
+            #[cfg(feature = "legacy")]
+            CompressionMethod::Reduce4 => 5,
+            #[cfg(feature = "legacy")]
+            CompressionMethod::Implode => 6,
             #[cfg(any(
                 feature = "deflate",
                 feature = "deflate-miniz",
                 feature = "deflate-zlib"
             ))]
             CompressionMethod::Deflated => 8,
+            #[cfg(feature = "deflate64")]
+            CompressionMethod::Deflate64 => 9,
+            #[cfg(feature = "legacy")]
+            CompressionMethod::PkwareImplode => 10,
             #[cfg(feature = "bzip2")]
             CompressionMethod::Bzip2 => 12,
+            #[cfg(feature = "lzma")]
+            CompressionMethod::Lzma => 14,
             CompressionMethod::AES => 99,
             #[cfg(feature = "zstd")]
             CompressionMethod::Zstd => 93,
+            #[cfg(feature = "xz")]
+            CompressionMethod::Xz => 95,
+            #[cfg(feature = "ppmd")]
+            CompressionMethod::Ppmd => 98,
 
             CompressionMethod::Unsupported(v) => v,
         }
@@ -148,8 +265,14 @@ pub const SUPPORTED_COMPRESSION_METHODS: &[CompressionMethod] = &[
     CompressionMethod::Deflated,
     #[cfg(feature = "bzip2")]
     CompressionMethod::Bzip2,
+    #[cfg(feature = "lzma")]
+    CompressionMethod::Lzma,
     #[cfg(feature = "zstd")]
     CompressionMethod::Zstd,
+    #[cfg(feature = "xz")]
+    CompressionMethod::Xz,
+    #[cfg(feature = "ppmd")]
+    CompressionMethod::Ppmd,
 ];
 
 #[cfg(test)]
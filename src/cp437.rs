@@ -35,6 +35,16 @@ impl FromCp437 for Vec<u8> {
     }
 }
 
+/// Encode a string as IBM codepage 437, if all of its characters can be represented
+pub(crate) fn to_cp437(text: &str) -> Option<Vec<u8>> {
+    text.chars()
+        .map(|c| match c {
+            '\x00'..='\x7f' => Some(c as u8),
+            _ => (0x80..=0xff).find(|&b| to_char(b) == c),
+        })
+        .collect()
+}
+
 fn to_char(input: u8) -> char {
     let output = match input {
         0x00..=0x7f => input as u32,
@@ -201,4 +211,13 @@ mod test {
         assert!(String::from_utf8(data.clone()).is_err());
         assert_eq!(&data.from_cp437(), "╠══╣");
     }
+
+    #[test]
+    fn round_trip() {
+        use super::FromCp437;
+        let data: Vec<u8> = (0x00..=0xff).collect();
+        assert_eq!(super::to_cp437(&data.clone().from_cp437()), Some(data));
+        assert_eq!(super::to_cp437("Curaçao"), Some(b"Cura\x87ao".to_vec()));
+        assert_eq!(super::to_cp437("日本語"), None);
+    }
 }
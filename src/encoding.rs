@@ -0,0 +1,152 @@
+//! Decoding of file names and comments that aren't flagged as UTF-8
+
+use crate::cp437::FromCp437;
+
+/// A decoder for the names and comments of files that aren't flagged as UTF-8
+///
+/// The ZIP specification only allows CP437 besides UTF-8, but many tools write names in the
+/// code page of the system they run on. A decoder for that code page can be set with
+/// [`ReadOptions::name_decoder`](crate::read::ReadOptions::name_decoder). Info-ZIP Unicode
+/// Path and Comment extra fields still take precedence over the decoded names.
+///
+/// Decoders are implemented for closures and [`Cp437`]. With the `encoding_rs` feature, they're
+/// also implemented for the encodings of that crate, such as `&encoding_rs::SHIFT_JIS`, and for
+/// `DetectEncoding`.
+///
+/// ```no_run
+/// use zip::read::ReadOptions;
+/// use zip::ZipArchive;
+///
+/// fn open(file: std::fs::File) -> zip::result::ZipResult<ZipArchive<std::fs::File>> {
+///     // Lossily decode names as UTF-8 instead of CP437
+///     let decoder = |raw: &[u8]| String::from_utf8_lossy(raw).into_owned();
+///     ZipArchive::with_options(file, ReadOptions::default().name_decoder(&decoder))
+/// }
+/// ```
+pub trait NameDecoder {
+    /// Decode a raw name or comment
+    fn decode(&self, raw: &[u8]) -> String;
+
+    /// Decode the names and comments of all files of an archive that aren't flagged as UTF-8,
+    /// in order.
+    ///
+    /// By default, each of them is decoded with [`NameDecoder::decode`]. Decoders that choose
+    /// one encoding for the whole archive override this.
+    fn decode_all(&self, raw: &[&[u8]]) -> Vec<String> {
+        raw.iter().map(|raw| self.decode(raw)).collect()
+    }
+}
+
+impl<F: Fn(&[u8]) -> String> NameDecoder for F {
+    fn decode(&self, raw: &[u8]) -> String {
+        self(raw)
+    }
+}
+
+/// The decoder for IBM code page 437, which is used by default
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Cp437;
+
+impl NameDecoder for Cp437 {
+    fn decode(&self, raw: &[u8]) -> String {
+        raw.from_cp437().into_owned()
+    }
+}
+
+/// Decodes with the encoding, replacing malformed sequences
+#[cfg(feature = "encoding_rs")]
+impl NameDecoder for &'static encoding_rs::Encoding {
+    fn decode(&self, raw: &[u8]) -> String {
+        (*self).decode_without_bom_handling(raw).0.into_owned()
+    }
+}
+
+/// A decoder that guesses one encoding for all names and comments of an archive from a list of
+/// candidates
+///
+/// If all names are valid UTF-8, they're decoded as UTF-8, since many tools write UTF-8 names
+/// without flagging them. Otherwise, the first candidate that decodes all of them without errors
+/// is used, and if no candidate can, they're decoded as CP437. Single byte encodings such as
+/// `IBM866` decode any name, so they should come last.
+///
+/// ```no_run
+/// use zip::read::{DetectEncoding, ReadOptions};
+/// use zip::ZipArchive;
+///
+/// fn open(file: std::fs::File) -> zip::result::ZipResult<ZipArchive<std::fs::File>> {
+///     let decoder = DetectEncoding::new(&[encoding_rs::SHIFT_JIS, encoding_rs::GBK]);
+///     ZipArchive::with_options(file, ReadOptions::default().name_decoder(&decoder))
+/// }
+/// ```
+#[cfg(feature = "encoding_rs")]
+#[derive(Clone, Debug)]
+pub struct DetectEncoding {
+    candidates: Vec<&'static encoding_rs::Encoding>,
+}
+
+#[cfg(feature = "encoding_rs")]
+impl DetectEncoding {
+    /// Create a decoder that tries the candidates in order
+    pub fn new(candidates: &[&'static encoding_rs::Encoding]) -> DetectEncoding {
+        DetectEncoding {
+            candidates: candidates.to_vec(),
+        }
+    }
+}
+
+#[cfg(feature = "encoding_rs")]
+impl NameDecoder for DetectEncoding {
+    fn decode(&self, raw: &[u8]) -> String {
+        self.decode_all(&[raw]).pop().unwrap_or_default()
+    }
+
+    fn decode_all(&self, raw: &[&[u8]]) -> Vec<String> {
+        let utf8: Option<Vec<String>> = raw
+            .iter()
+            .map(|raw| std::str::from_utf8(raw).ok().map(str::to_owned))
+            .collect();
+        if let Some(decoded) = utf8 {
+            return decoded;
+        }
+        for encoding in self.candidates.iter() {
+            let decoded: Option<Vec<String>> = raw
+                .iter()
+                .map(|raw| {
+                    encoding
+                        .decode_without_bom_handling_and_without_replacement(raw)
+                        .map(|text| text.into_owned())
+                })
+                .collect();
+            if let Some(decoded) = decoded {
+                return decoded;
+            }
+        }
+        Cp437.decode_all(raw)
+    }
+}
+
+#[cfg(all(test, feature = "encoding_rs"))]
+mod test {
+    use super::{DetectEncoding, NameDecoder};
+
+    #[test]
+    fn detect_encoding() {
+        let decoder = DetectEncoding::new(&[encoding_rs::SHIFT_JIS, encoding_rs::IBM866]);
+        assert_eq!(decoder.decode("日本語.txt".as_bytes()), "日本語.txt");
+        // "日本語" in Shift-JIS
+        assert_eq!(decoder.decode(b"\x93\xfa\x96\x7b\x8c\xea"), "日本語");
+        // "Анна" in CP866, which isn't valid Shift-JIS
+        assert_eq!(decoder.decode(b"\x80\xad\xad\xa0"), "Анна");
+    }
+
+    #[test]
+    fn one_encoding_per_archive() {
+        let decoder = DetectEncoding::new(&[encoding_rs::SHIFT_JIS, encoding_rs::IBM866]);
+        // "é" in UTF-8, and "Анна" in CP866, which isn't valid UTF-8 or Shift-JIS
+        let decoded = decoder.decode_all(&[&b"\xc3\xa9"[..], b"\x80\xad\xad\xa0"]);
+        assert_eq!(decoded, ["├й", "Анна"]);
+        // "日本" and "あ" in Shift-JIS, the second of which is valid CP866 too
+        let decoded = decoder.decode_all(&[&b"\x93\xfa\x96\x7b"[..], b"\x82\xa0"]);
+        assert_eq!(decoded, ["日本", "あ"]);
+    }
+}
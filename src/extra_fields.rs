@@ -0,0 +1,477 @@
+//! Typed access to the extra fields of files
+//!
+//! The extra field of a local or central file header is a sequence of records, each starting
+//! with a 2 byte ID and a 2 byte size. [`ExtraFields`] iterates over these records, and parses
+//! the kinds it knows into an [`ExtraField`].
+//!
+//! ```no_run
+//! use zip::extra_fields::ExtraField;
+//!
+//! fn print_owner(file: &zip::read::ZipFile) -> zip::result::ZipResult<()> {
+//!     for field in file.extra_fields() {
+//!         if let ExtraField::UnixOwner { uid, gid } = field? {
+//!             println!("{} is owned by {}:{}", file.name(), uid, gid);
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::compression::CompressionMethod;
+use crate::result::{ZipError, ZipResult};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+pub use crate::types::{AesMode, AesVendorVersion};
+
+/// A record of an extra field
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExtraField<'a> {
+    /// ZIP64 extended information (0x0001)
+    ///
+    /// Contains the 8 byte values of the uncompressed size, compressed size and offset of the
+    /// local header, in this order. Each is only present if the corresponding field of the
+    /// header is `0xFFFFFFFF`, so the values can't be told apart without the header.
+    Zip64(Vec<u64>),
+    /// NTFS file times (0x000a), in 100 nanosecond intervals since January 1, 1601 UTC
+    Ntfs {
+        /// The last modification time
+        modified: u64,
+        /// The last access time
+        accessed: u64,
+        /// The creation time
+        created: u64,
+    },
+    /// Extended timestamp (0x5455), in seconds since January 1, 1970 UTC
+    ///
+    /// Central headers usually only contain the modification time.
+    ExtendedTimestamp {
+        /// The last modification time
+        modified: Option<i32>,
+        /// The last access time
+        accessed: Option<i32>,
+        /// The creation time
+        created: Option<i32>,
+    },
+    /// Info-ZIP Unix user and group ID (0x7875)
+    UnixOwner {
+        /// The user ID
+        uid: u64,
+        /// The group ID
+        gid: u64,
+    },
+    /// Info-ZIP Unicode path (0x7075)
+    UnicodePath {
+        /// The CRC-32 of the file name in the header, to detect whether it was changed without
+        /// updating this record
+        crc32: u32,
+        /// The file name, in UTF-8
+        name: &'a str,
+    },
+    /// Info-ZIP Unicode comment (0x6375)
+    UnicodeComment {
+        /// The CRC-32 of the file comment in the central header, to detect whether it was
+        /// changed without updating this record
+        crc32: u32,
+        /// The file comment, in UTF-8
+        comment: &'a str,
+    },
+    /// AES encryption (0x9901)
+    Aes {
+        /// The version of the AES format
+        vendor_version: AesVendorVersion,
+        /// The strength of the encryption
+        mode: AesMode,
+        /// The compression method of the file, which is replaced by 99 in the header
+        compression_method: CompressionMethod,
+    },
+    /// Padding that aligns the data of a file, as written by
+    /// [`ZipWriter::start_file_aligned`](crate::ZipWriter::start_file_aligned) (0x617a)
+    ///
+    /// Contains the number of padding bytes.
+    Padding(u16),
+    /// Padding that aligns the data of a file, as written by Android's zipalign (0xd935)
+    Alignment {
+        /// The alignment of the data, in bytes
+        alignment: u16,
+        /// The number of padding bytes
+        padding: u16,
+    },
+    /// A record of a kind that isn't parsed, with its ID and data
+    Unknown(u16, &'a [u8]),
+}
+
+impl<'a> ExtraField<'a> {
+    /// Create an Info-ZIP Unicode Path record for a file whose name is written in the header as
+    /// `raw_name`, such as one started with
+    /// [`ZipWriter::start_file_raw_name`](crate::ZipWriter::start_file_raw_name).
+    pub fn unicode_path(raw_name: &[u8], name: &'a str) -> ExtraField<'a> {
+        ExtraField::UnicodePath {
+            crc32: crc32fast::hash(raw_name),
+            name,
+        }
+    }
+
+    /// Get the ID of the record
+    pub fn id(&self) -> u16 {
+        match self {
+            ExtraField::Zip64(_) => 0x0001,
+            ExtraField::Ntfs { .. } => 0x000a,
+            ExtraField::ExtendedTimestamp { .. } => 0x5455,
+            ExtraField::UnixOwner { .. } => 0x7875,
+            ExtraField::UnicodePath { .. } => 0x7075,
+            ExtraField::UnicodeComment { .. } => 0x6375,
+            ExtraField::Aes { .. } => 0x9901,
+            ExtraField::Padding(_) => 0x617a,
+            ExtraField::Alignment { .. } => 0xd935,
+            ExtraField::Unknown(id, _) => *id,
+        }
+    }
+
+    /// Append the record to an extra field.
+    pub(crate) fn write_to(&self, output: &mut Vec<u8>) -> io::Result<()> {
+        let mut data = Vec::new();
+        match self {
+            ExtraField::Zip64(values) => {
+                for &value in values {
+                    data.write_u64::<LittleEndian>(value)?;
+                }
+            }
+            ExtraField::Ntfs {
+                modified,
+                accessed,
+                created,
+            } => {
+                data.write_u32::<LittleEndian>(0)?;
+                data.write_u16::<LittleEndian>(0x0001)?;
+                data.write_u16::<LittleEndian>(24)?;
+                for &time in &[modified, accessed, created] {
+                    data.write_u64::<LittleEndian>(*time)?;
+                }
+            }
+            ExtraField::ExtendedTimestamp {
+                modified,
+                accessed,
+                created,
+            } => {
+                let times = [modified, accessed, created];
+                let flags = (0..3).filter(|&i| times[i].is_some()).map(|i| 1 << i).sum();
+                data.write_u8(flags)?;
+                for time in times.iter().filter_map(|time| time.as_ref()) {
+                    data.write_i32::<LittleEndian>(*time)?;
+                }
+            }
+            ExtraField::UnixOwner { uid, gid } => {
+                data.write_u8(1)?;
+                for &id in &[uid, gid] {
+                    if *id > u32::MAX as u64 {
+                        data.write_u8(8)?;
+                        data.write_u64::<LittleEndian>(*id)?;
+                    } else {
+                        data.write_u8(4)?;
+                        data.write_u32::<LittleEndian>(*id as u32)?;
+                    }
+                }
+            }
+            ExtraField::UnicodePath { crc32, name: text }
+            | ExtraField::UnicodeComment {
+                crc32,
+                comment: text,
+            } => {
+                data.write_u8(1)?;
+                data.write_u32::<LittleEndian>(*crc32)?;
+                data.write_all(text.as_bytes())?;
+            }
+            ExtraField::Aes {
+                vendor_version,
+                mode,
+                compression_method,
+            } => {
+                data.write_u16::<LittleEndian>(match vendor_version {
+                    AesVendorVersion::Ae1 => 0x0001,
+                    AesVendorVersion::Ae2 => 0x0002,
+                })?;
+                data.write_all(b"AE")?;
+                data.write_u8(match mode {
+                    AesMode::Aes128 => 0x01,
+                    AesMode::Aes192 => 0x02,
+                    AesMode::Aes256 => 0x03,
+                })?;
+                #[allow(deprecated)]
+                data.write_u16::<LittleEndian>(compression_method.to_u16())?;
+            }
+            ExtraField::Padding(padding) => data.resize(*padding as usize, 0),
+            ExtraField::Alignment { alignment, padding } => {
+                data.write_u16::<LittleEndian>(*alignment)?;
+                data.resize(2 + *padding as usize, 0);
+            }
+            ExtraField::Unknown(_, bytes) => data.extend_from_slice(bytes),
+        }
+        if data.len() > 0xFFFF {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Extra field record too large",
+            ));
+        }
+        output.write_u16::<LittleEndian>(self.id())?;
+        output.write_u16::<LittleEndian>(data.len() as u16)?;
+        output.write_all(&data)
+    }
+
+    fn parse(id: u16, mut data: &'a [u8]) -> io::Result<ExtraField<'a>> {
+        let field = match id {
+            0x0001 => {
+                let mut values = Vec::new();
+                while data.len() >= 8 {
+                    values.push(data.read_u64::<LittleEndian>()?);
+                }
+                if data.len() == 4 {
+                    // The number of the disk the file starts on isn't needed
+                    data = &[];
+                }
+                ExtraField::Zip64(values)
+            }
+            0x000a => {
+                let _reserved = data.read_u32::<LittleEndian>()?;
+                loop {
+                    let tag = data.read_u16::<LittleEndian>()?;
+                    let size = data.read_u16::<LittleEndian>()? as usize;
+                    if tag == 0x0001 && size == 24 {
+                        break ExtraField::Ntfs {
+                            modified: data.read_u64::<LittleEndian>()?,
+                            accessed: data.read_u64::<LittleEndian>()?,
+                            created: data.read_u64::<LittleEndian>()?,
+                        };
+                    }
+                    data = data.get(size..).ok_or_else(invalid)?;
+                }
+            }
+            0x5455 => {
+                let flags = data.read_u8()?;
+                // The flags tell which times the local header contains, but central headers may
+                // omit all except the modification time
+                let mut time = |bit: u8| match (flags & bit != 0, data.is_empty()) {
+                    (true, false) => data.read_i32::<LittleEndian>().map(Some),
+                    _ => Ok(None),
+                };
+                ExtraField::ExtendedTimestamp {
+                    modified: time(1)?,
+                    accessed: time(2)?,
+                    created: time(4)?,
+                }
+            }
+            0x7875 => {
+                if data.read_u8()? != 1 {
+                    return Err(invalid());
+                }
+                ExtraField::UnixOwner {
+                    uid: read_variable_uint(&mut data)?,
+                    gid: read_variable_uint(&mut data)?,
+                }
+            }
+            0x7075 | 0x6375 => {
+                if data.read_u8()? != 1 {
+                    return Err(invalid());
+                }
+                let crc32 = data.read_u32::<LittleEndian>()?;
+                let text = std::str::from_utf8(data).map_err(|_| invalid())?;
+                data = &[];
+                match id {
+                    0x7075 => ExtraField::UnicodePath { crc32, name: text },
+                    _ => ExtraField::UnicodeComment {
+                        crc32,
+                        comment: text,
+                    },
+                }
+            }
+            0x9901 => {
+                let vendor_version = match data.read_u16::<LittleEndian>()? {
+                    0x0001 => AesVendorVersion::Ae1,
+                    0x0002 => AesVendorVersion::Ae2,
+                    _ => return Err(invalid()),
+                };
+                if data.read_u16::<LittleEndian>()? != u16::from_le_bytes(*b"AE") {
+                    return Err(invalid());
+                }
+                let mode = match data.read_u8()? {
+                    0x01 => AesMode::Aes128,
+                    0x02 => AesMode::Aes192,
+                    0x03 => AesMode::Aes256,
+                    _ => return Err(invalid()),
+                };
+                #[allow(deprecated)]
+                let compression_method =
+                    CompressionMethod::from_u16(data.read_u16::<LittleEndian>()?);
+                ExtraField::Aes {
+                    vendor_version,
+                    mode,
+                    compression_method,
+                }
+            }
+            0x617a => {
+                let padding = data.len() as u16;
+                data = &[];
+                ExtraField::Padding(padding)
+            }
+            0xd935 => {
+                let alignment = data.read_u16::<LittleEndian>()?;
+                let padding = data.len() as u16;
+                data = &[];
+                ExtraField::Alignment { alignment, padding }
+            }
+            _ => {
+                return Ok(ExtraField::Unknown(id, data));
+            }
+        };
+        if !data.is_empty() && id != 0x000a {
+            return Err(invalid());
+        }
+        Ok(field)
+    }
+}
+
+/// Read an unsigned integer that's preceded by its size in bytes.
+fn read_variable_uint(data: &mut &[u8]) -> io::Result<u64> {
+    let size = data.read_u8()? as usize;
+    if size > 8 {
+        return Err(invalid());
+    }
+    let mut bytes = [0; 8];
+    data.read_exact(&mut bytes[..size])?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn invalid() -> io::Error {
+    io::ErrorKind::InvalidData.into()
+}
+
+/// An iterator over the records of an extra field
+///
+/// Created by [`ZipFile::extra_fields`](crate::read::ZipFile::extra_fields) and
+/// [`ZipFile::local_extra_fields`](crate::read::ZipFile::local_extra_fields), or from raw extra
+/// data with [`ExtraFields::new`]. A record that can't be parsed is returned as an error, and
+/// iteration continues with the next record if its size could be read.
+#[derive(Clone, Debug)]
+pub struct ExtraFields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ExtraFields<'a> {
+    /// Iterate over the records of raw extra data
+    pub fn new(data: &'a [u8]) -> ExtraFields<'a> {
+        ExtraFields { data }
+    }
+}
+
+impl<'a> Iterator for ExtraFields<'a> {
+    type Item = ZipResult<ExtraField<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let mut header = self.data;
+        let record = match (
+            header.read_u16::<LittleEndian>(),
+            header.read_u16::<LittleEndian>(),
+        ) {
+            (Ok(id), Ok(size)) => header.get(..size as usize).map(|data| (id, data)),
+            _ => None,
+        };
+        let (id, data) = match record {
+            Some(record) => record,
+            None => {
+                self.data = &[];
+                return Some(Err(ZipError::InvalidArchive("Truncated extra field")));
+            }
+        };
+        self.data = &header[data.len()..];
+        Some(
+            ExtraField::parse(id, data)
+                .map_err(|_| ZipError::InvalidArchive("Invalid extra field record")),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExtraField, ExtraFields};
+
+    #[test]
+    fn records() {
+        let mut data = Vec::new();
+        // Extended timestamp with all three flags, but only the modification time
+        data.extend_from_slice(b"UT\x05\x00\x07\x01\x02\x03\x04");
+        // Unix owner with a 2 byte uid and a 4 byte gid
+        data.extend_from_slice(b"ux\x09\x00\x01\x02\xe8\x03\x04\xe9\x03\x00\x00");
+        data.extend_from_slice(b"up\x08\x00\x01\x78\x56\x34\x12abc");
+        data.extend_from_slice(b"\xfe\xca\x02\x00xy");
+
+        let fields: Vec<_> = ExtraFields::new(&data).map(Result::unwrap).collect();
+        assert_eq!(
+            fields,
+            vec![
+                ExtraField::ExtendedTimestamp {
+                    modified: Some(0x04030201),
+                    accessed: None,
+                    created: None,
+                },
+                ExtraField::UnixOwner {
+                    uid: 1000,
+                    gid: 1001
+                },
+                ExtraField::UnicodePath {
+                    crc32: 0x12345678,
+                    name: "abc"
+                },
+                ExtraField::Unknown(0xcafe, b"xy"),
+            ]
+        );
+    }
+
+    #[test]
+    fn write_records() {
+        let fields = vec![
+            ExtraField::Ntfs {
+                modified: 1,
+                accessed: 2,
+                created: 3,
+            },
+            ExtraField::ExtendedTimestamp {
+                modified: Some(-1),
+                accessed: None,
+                created: Some(5),
+            },
+            ExtraField::UnixOwner {
+                uid: 1 << 40,
+                gid: 20,
+            },
+            ExtraField::UnicodeComment {
+                crc32: 7,
+                comment: "kommentár",
+            },
+            ExtraField::Padding(3),
+            ExtraField::Alignment {
+                alignment: 4096,
+                padding: 2,
+            },
+            ExtraField::Unknown(0xcafe, b"xy"),
+        ];
+        let mut data = Vec::new();
+        for field in &fields {
+            field.write_to(&mut data).unwrap();
+        }
+        let parsed: Vec<_> = ExtraFields::new(&data).map(Result::unwrap).collect();
+        assert_eq!(parsed, fields);
+    }
+
+    #[test]
+    fn invalid_records() {
+        // A unicode path with an unknown version, followed by a truncated record
+        let data = b"up\x06\x00\x02\x00\x00\x00\x00a\xfe\xca\x05\x00xy";
+        let mut fields = ExtraFields::new(data);
+        assert!(fields.next().unwrap().is_err());
+        assert!(fields.next().unwrap().is_err());
+        assert!(fields.next().is_none());
+    }
+}
@@ -0,0 +1,238 @@
+//! Options for extracting archives into a directory
+
+use crate::extra_fields::ExtraField;
+use crate::read::ZipFile;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// What to do when a file to be extracted already exists
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Replace the existing file
+    Always,
+    /// Keep the existing file and don't extract the new one
+    Skip,
+    /// Fail the extraction with an [`io::ErrorKind::AlreadyExists`] error
+    Error,
+    /// Replace the existing file only if it was last modified before the file in the archive
+    ///
+    /// The modification time of the file in the archive is taken from its extended timestamp
+    /// extra field if it has one, which is in UTC. Otherwise the MS-DOS time in its header is
+    /// used, which is in the local time of the system that wrote the archive. That time zone
+    /// isn't recorded, so the time is interpreted as UTC, and may be off by hours. The MS-DOS
+    /// time is only used with the `time` feature, without it the existing file is kept.
+    IfNewer,
+}
+
+/// Options for [`ZipArchive::extract_with`](crate::ZipArchive::extract_with)
+///
+/// The defaults match [`ZipArchive::extract`](crate::ZipArchive::extract).
+///
+/// ```no_run
+/// use std::path::Path;
+/// use zip::read::{ExtractOptions, Overwrite};
+///
+/// fn extract_text(archive: &mut zip::ZipArchive<std::fs::File>) -> zip::result::ZipResult<()> {
+///     let only_text = |path: &Path| path.extension() == Some("txt".as_ref());
+///     let options = ExtractOptions::default()
+///         .overwrite(Overwrite::Skip)
+///         .umask(0o022)
+///         .filter(&only_text);
+///     archive.extract_with("output", options)
+/// }
+/// ```
+#[derive(Copy, Clone)]
+pub struct ExtractOptions<'a> {
+    pub(crate) overwrite: Overwrite,
+    #[cfg_attr(not(feature = "filetime"), allow(dead_code))]
+    pub(crate) restore_modified_time: bool,
+    pub(crate) umask: u32,
+    pub(crate) directory_mode: Option<u32>,
+    pub(crate) filter: Option<&'a (dyn Fn(&Path) -> bool + Sync)>,
+}
+
+impl<'a> Default for ExtractOptions<'a> {
+    fn default() -> Self {
+        ExtractOptions {
+            overwrite: Overwrite::Always,
+            restore_modified_time: false,
+            umask: 0,
+            directory_mode: None,
+            filter: None,
+        }
+    }
+}
+
+impl<'a> ExtractOptions<'a> {
+    /// Set what to do when a file already exists. The default is [`Overwrite::Always`].
+    #[must_use]
+    pub fn overwrite(mut self, overwrite: Overwrite) -> ExtractOptions<'a> {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Set whether to set the modification time of extracted files to their last modified time
+    /// in the archive, which is determined as for [`Overwrite::IfNewer`]. The default is `false`.
+    #[cfg(feature = "filetime")]
+    #[must_use]
+    pub fn restore_modified_time(mut self, restore: bool) -> ExtractOptions<'a> {
+        self.restore_modified_time = restore;
+        self
+    }
+
+    /// Set the permission bits to clear from the unix mode of extracted files and directories.
+    ///
+    /// For example, `0o022` removes write permission for the group and others. The default is
+    /// `0`, which applies the mode stored in the archive as it is. This has no effect on other
+    /// platforms.
+    #[must_use]
+    pub fn umask(mut self, mask: u32) -> ExtractOptions<'a> {
+        self.umask = mask & 0o7777;
+        self
+    }
+
+    /// Set whether to clear the setuid, setgid and sticky bits from the unix mode of extracted
+    /// files and directories. The default is `false`.
+    #[must_use]
+    pub fn strip_special_bits(mut self, strip: bool) -> ExtractOptions<'a> {
+        if strip {
+            self.umask |= 0o7000;
+        } else {
+            self.umask &= !0o7000;
+        }
+        self
+    }
+
+    /// Set the unix mode of directories that have to be created, but don't have an entry in
+    /// the archive to take their mode from. It is subject to the process umask.
+    ///
+    /// The default is `None`, which creates them with the default mode. This has no effect on
+    /// other platforms.
+    #[must_use]
+    pub fn directory_mode(mut self, mode: Option<u32>) -> ExtractOptions<'a> {
+        self.directory_mode = mode.map(|mode| mode & 0o7777);
+        self
+    }
+
+    /// Set a predicate that decides which entries to extract, given their path relative to the
+    /// output directory. The default extracts all entries.
+    ///
+    /// Directories are still created as needed for the files that are extracted.
+    #[must_use]
+    pub fn filter(mut self, filter: &'a (dyn Fn(&Path) -> bool + Sync)) -> ExtractOptions<'a> {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// The same options without the borrowed filter, so that they can be moved to other threads.
+    pub(crate) fn without_filter(&self) -> ExtractOptions<'static> {
+        ExtractOptions {
+            overwrite: self.overwrite,
+            restore_modified_time: self.restore_modified_time,
+            umask: self.umask,
+            directory_mode: self.directory_mode,
+            filter: None,
+        }
+    }
+
+    pub(crate) fn includes(&self, path: &Path) -> bool {
+        match self.filter {
+            Some(filter) => filter(path),
+            None => true,
+        }
+    }
+
+    pub(crate) fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            if let Some(mode) = self.directory_mode {
+                builder.mode(mode);
+            }
+        }
+        builder.create(path)
+    }
+
+    /// Create a file to extract into, or return `None` if an existing file should be kept.
+    pub(crate) fn create_file(
+        &self,
+        path: &Path,
+        modified: Option<SystemTime>,
+    ) -> io::Result<Option<fs::File>> {
+        let create_new = || {
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+        };
+        match self.overwrite {
+            Overwrite::Always => fs::File::create(path).map(Some),
+            Overwrite::Error => create_new().map(Some),
+            Overwrite::Skip => match create_new() {
+                Ok(file) => Ok(Some(file)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(None),
+                Err(e) => Err(e),
+            },
+            Overwrite::IfNewer => {
+                let existing = match fs::metadata(path) {
+                    Ok(metadata) => metadata.modified()?,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        return fs::File::create(path).map(Some);
+                    }
+                    Err(e) => return Err(e),
+                };
+                match modified {
+                    Some(time) if time > existing => fs::File::create(path).map(Some),
+                    _ => Ok(None),
+                }
+            }
+        }
+    }
+
+    /// Apply the umask to a unix mode from the archive.
+    #[cfg(unix)]
+    pub(crate) fn mask(&self, mode: u32) -> u32 {
+        mode & !self.umask
+    }
+
+    /// Set the modification time of an extracted file, if that was requested.
+    #[cfg(feature = "filetime")]
+    pub(crate) fn set_modified_time(
+        &self,
+        file: &fs::File,
+        modified: Option<SystemTime>,
+    ) -> io::Result<()> {
+        if let (true, Some(time)) = (self.restore_modified_time, modified) {
+            let time = filetime::FileTime::from_system_time(time);
+            filetime::set_file_handle_times(file, None, Some(time))?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the last modification time of a file in the archive, see [`Overwrite::IfNewer`].
+pub(crate) fn modified_time(file: &ZipFile<'_>) -> Option<SystemTime> {
+    let extended = file.extra_fields().find_map(|field| match field {
+        Ok(ExtraField::ExtendedTimestamp {
+            modified: Some(modified),
+            ..
+        }) => Some(modified),
+        _ => None,
+    });
+    if let Some(modified) = extended {
+        let seconds = Duration::from_secs(modified.unsigned_abs() as u64);
+        return if modified >= 0 {
+            UNIX_EPOCH.checked_add(seconds)
+        } else {
+            UNIX_EPOCH.checked_sub(seconds)
+        };
+    }
+    #[cfg(feature = "time")]
+    return file.last_modified().to_time().ok().map(SystemTime::from);
+    #[cfg(not(feature = "time"))]
+    None
+}
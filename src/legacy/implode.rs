@@ -0,0 +1,64 @@
+//! Implode (method 6): a sliding dictionary of 4 or 8 KiB with Shannon-Fano coded lengths and
+//! distances, and optionally Shannon-Fano coded literals.
+//!
+//! Bit 1 of the general purpose flag selects the 8 KiB dictionary, and bit 2 the literal tree.
+//! The trees are stored at the start of the data as run-lengths of code lengths.
+
+use super::{copy_match, invalid_data, BitReader, Huffman};
+use std::io;
+
+fn read_tree(bits: &mut BitReader, symbols: usize) -> io::Result<Huffman> {
+    let mut lengths = Vec::with_capacity(symbols);
+    let bytes = bits.bits(8)? + 1;
+    for _ in 0..bytes {
+        let byte = bits.bits(8)?;
+        let length = (byte & 0xf) as u8 + 1;
+        let count = (byte >> 4) + 1;
+        for _ in 0..count {
+            lengths.push(length);
+        }
+    }
+    if lengths.len() != symbols {
+        return Err(invalid_data("Invalid tree in imploded data"));
+    }
+    Huffman::new(&lengths)
+}
+
+pub(super) fn explode(
+    compressed: &[u8],
+    size: usize,
+    large_dictionary: bool,
+    literal_tree: bool,
+) -> io::Result<Vec<u8>> {
+    let mut bits = BitReader::new(compressed);
+    let literals = if literal_tree {
+        Some(read_tree(&mut bits, 256)?)
+    } else {
+        None
+    };
+    let lengths = read_tree(&mut bits, 64)?;
+    let distances = read_tree(&mut bits, 64)?;
+
+    let distance_low_bits = if large_dictionary { 7 } else { 6 };
+    let min_length = if literal_tree { 3 } else { 2 };
+
+    let mut output = Vec::new();
+    while output.len() < size {
+        if bits.bits(1)? == 1 {
+            let byte = match &literals {
+                Some(literals) => literals.decode(&mut bits)? as u8,
+                None => bits.bits(8)? as u8,
+            };
+            output.push(byte);
+        } else {
+            let low = bits.bits(distance_low_bits)? as usize;
+            let distance = ((distances.decode(&mut bits)? as usize) << distance_low_bits | low) + 1;
+            let mut length = lengths.decode(&mut bits)? as usize;
+            if length == 63 {
+                length += bits.bits(8)? as usize;
+            }
+            copy_match(&mut output, distance, length + min_length, size);
+        }
+    }
+    Ok(output)
+}
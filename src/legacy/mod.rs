@@ -0,0 +1,217 @@
+//! Decompression of the compression methods used by PKZIP 1.x and PKWARE's Data Compression
+//! Library: Shrink (method 1), Reduce (methods 2 to 5), Implode (method 6) and DCL Implode
+//! (method 10).
+//!
+//! None of these formats mark the end of their data, so the entry is decompressed at once up to
+//! its uncompressed size on the first read. Both the compressed and the decompressed data are held
+//! in memory until the reader is dropped, see [`Limits`](crate::read::Limits) for bounding them.
+
+mod implode;
+mod pkware_implode;
+mod reduce;
+mod shrink;
+
+use crate::compression::CompressionMethod;
+use std::convert::TryFrom;
+use std::io::{self, Read};
+
+/// Reader for the data of a zip file compressed with a legacy compression method.
+pub struct LegacyReader<R> {
+    inner: R,
+    compression_method: CompressionMethod,
+    flags: u16,
+    uncompressed_size: u64,
+    decompressed: Option<io::Cursor<Vec<u8>>>,
+}
+
+impl<R: Read> LegacyReader<R> {
+    pub fn new(
+        inner: R,
+        compression_method: CompressionMethod,
+        flags: u16,
+        uncompressed_size: u64,
+    ) -> LegacyReader<R> {
+        LegacyReader {
+            inner,
+            compression_method,
+            flags,
+            uncompressed_size,
+            decompressed: None,
+        }
+    }
+
+    fn decompress(&mut self) -> io::Result<Vec<u8>> {
+        let mut compressed = Vec::new();
+        self.inner.read_to_end(&mut compressed)?;
+        let size = usize::try_from(self.uncompressed_size)
+            .map_err(|_| invalid_data("Uncompressed size is too large"))?;
+
+        match self.compression_method {
+            CompressionMethod::Shrink => shrink::unshrink(&compressed, size),
+            CompressionMethod::Reduce1 => reduce::unreduce(&compressed, size, 1),
+            CompressionMethod::Reduce2 => reduce::unreduce(&compressed, size, 2),
+            CompressionMethod::Reduce3 => reduce::unreduce(&compressed, size, 3),
+            CompressionMethod::Reduce4 => reduce::unreduce(&compressed, size, 4),
+            CompressionMethod::Implode => implode::explode(
+                &compressed,
+                size,
+                self.flags & (1 << 1) != 0,
+                self.flags & (1 << 2) != 0,
+            ),
+            CompressionMethod::PkwareImplode => pkware_implode::explode(&compressed, size),
+            _ => Err(invalid_data("Not a legacy compression method")),
+        }
+    }
+
+    /// Consumes this decoder, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for LegacyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.decompressed.is_none() {
+            self.decompressed = Some(io::Cursor::new(self.decompress()?));
+        }
+        self.decompressed.as_mut().unwrap().read(buf)
+    }
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Reads bits least significant bit first, as all of the legacy methods do.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader {
+            data,
+            position: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    /// Reads `n` bits, with `n` at most 16.
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        while self.count < n {
+            let byte = *self
+                .data
+                .get(self.position)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+            self.position += 1;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+}
+
+/// Shannon-Fano or Huffman code as used by Implode and DCL Implode.
+///
+/// Both store their codes with the bits inverted relative to canonical Huffman codes, which are
+/// assigned in order of code length and then symbol.
+struct Huffman {
+    /// Number of symbols of each code length
+    counts: [u16; 17],
+    /// Symbols ordered by code length and then by value
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Huffman> {
+        let mut counts = [0; 17];
+        for &length in lengths {
+            if length > 16 {
+                return Err(invalid_data("Invalid code length"));
+            }
+            counts[length as usize] += 1;
+        }
+
+        // Reject over-subscribed codes, they would make decoding ambiguous
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(invalid_data("Over-subscribed code lengths"));
+            }
+        }
+
+        let mut symbols = Vec::with_capacity(lengths.len());
+        for length in 1..=16 {
+            for (symbol, &l) in lengths.iter().enumerate() {
+                if l == length {
+                    symbols.push(symbol as u16);
+                }
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> io::Result<u16> {
+        let mut code = 0;
+        let mut first = 0;
+        let mut index = 0;
+        for &count in &self.counts[1..] {
+            code |= bits.bits(1)? as i32 ^ 1;
+            let count = count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid_data("Invalid code"))
+    }
+}
+
+/// Copies `length` bytes starting `distance` bytes back, treating the bytes before the start of
+/// the output as zeros like PKZIP does, without growing `output` beyond `size`.
+fn copy_match(output: &mut Vec<u8>, distance: usize, length: usize, size: usize) {
+    for _ in 0..length.min(size - output.len()) {
+        let byte = match output.len().checked_sub(distance) {
+            Some(position) => output[position],
+            None => 0,
+        };
+        output.push(byte);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{copy_match, BitReader, Huffman};
+
+    #[test]
+    fn bits() {
+        let mut bits = BitReader::new(&[0b1010_1100, 0xff]);
+        assert_eq!(bits.bits(3).unwrap(), 0b100);
+        assert_eq!(bits.bits(9).unwrap(), 0b1_1111_0101);
+        assert_eq!(bits.bits(4).unwrap(), 0b1111);
+        assert!(bits.bits(1).is_err());
+    }
+
+    #[test]
+    fn over_subscribed_code() {
+        assert!(Huffman::new(&[1, 1, 1]).is_err());
+        assert!(Huffman::new(&[1, 2, 2]).is_ok());
+    }
+
+    #[test]
+    fn copy_before_start() {
+        let mut output = b"ab".to_vec();
+        copy_match(&mut output, 3, 5, 6);
+        assert_eq!(output, b"ab\0ab\0");
+    }
+}
@@ -0,0 +1,111 @@
+//! DCL Implode (method 10), the format of PKWARE's Data Compression Library.
+//!
+//! The data starts with two bytes: whether literals are coded, and the number of low distance
+//! bits (4 to 6, for a dictionary of 1 to 4 KiB). The codes themselves are fixed.
+
+use super::{copy_match, invalid_data, BitReader, Huffman};
+use std::io;
+
+/// Code lengths of the literals, as run-lengths of (count - 1) << 4 | length
+const LITERAL_LENGTHS: [u8; 98] = [
+    11, 124, 8, 7, 28, 7, 188, 13, 76, 4, 10, 8, 12, 10, 12, 10, 8, 23, 8, 9, 7, 6, 7, 8, 7, 6, 55,
+    8, 23, 24, 12, 11, 7, 9, 11, 12, 6, 7, 22, 5, 7, 24, 6, 11, 9, 6, 7, 22, 7, 11, 38, 7, 9, 8,
+    25, 11, 8, 11, 9, 12, 8, 12, 5, 38, 5, 38, 5, 11, 7, 5, 6, 21, 6, 10, 53, 8, 7, 24, 10, 27, 44,
+    253, 253, 253, 252, 252, 252, 13, 12, 45, 12, 45, 12, 61, 12, 45, 44, 173,
+];
+/// Code lengths of the length symbols
+const LENGTH_LENGTHS: [u8; 6] = [2, 35, 36, 53, 38, 23];
+/// Code lengths of the high distance bits
+const DISTANCE_LENGTHS: [u8; 7] = [2, 20, 53, 230, 247, 151, 248];
+
+const LENGTH_BASE: [u16; 16] = [3, 2, 4, 5, 6, 7, 8, 9, 10, 12, 16, 24, 40, 72, 136, 264];
+const LENGTH_EXTRA: [u8; 16] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5, 6, 7, 8];
+
+/// Length which marks the end of the data
+const END_OF_STREAM: usize = 519;
+
+fn expand_lengths(run_lengths: &[u8]) -> Vec<u8> {
+    let mut lengths = Vec::new();
+    for &byte in run_lengths {
+        for _ in 0..(byte >> 4) + 1 {
+            lengths.push(byte & 0xf);
+        }
+    }
+    lengths
+}
+
+pub(super) fn explode(compressed: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    let mut bits = BitReader::new(compressed);
+    let coded_literals = match bits.bits(8)? {
+        0 => false,
+        1 => true,
+        _ => return Err(invalid_data("Invalid literal mode in imploded data")),
+    };
+    let dictionary_bits = bits.bits(8)?;
+    if !(4..=6).contains(&dictionary_bits) {
+        return Err(invalid_data("Invalid dictionary size in imploded data"));
+    }
+
+    let literals = if coded_literals {
+        Some(Huffman::new(&expand_lengths(&LITERAL_LENGTHS))?)
+    } else {
+        None
+    };
+    let lengths = Huffman::new(&expand_lengths(&LENGTH_LENGTHS))?;
+    let distances = Huffman::new(&expand_lengths(&DISTANCE_LENGTHS))?;
+
+    let mut output = Vec::new();
+    while output.len() < size {
+        if bits.bits(1)? == 1 {
+            let symbol = lengths.decode(&mut bits)? as usize;
+            let length =
+                LENGTH_BASE[symbol] as usize + bits.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+            if length == END_OF_STREAM {
+                break;
+            }
+            let low_bits = if length == 2 { 2 } else { dictionary_bits };
+            let distance = ((distances.decode(&mut bits)? as usize) << low_bits
+                | bits.bits(low_bits)? as usize)
+                + 1;
+            if distance > output.len() {
+                return Err(invalid_data("Distance too far back in imploded data"));
+            }
+            copy_match(&mut output, distance, length, size);
+        } else {
+            let byte = match &literals {
+                Some(literals) => literals.decode(&mut bits)? as u8,
+                None => bits.bits(8)? as u8,
+            };
+            output.push(byte);
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{expand_lengths, explode, DISTANCE_LENGTHS, LENGTH_LENGTHS, LITERAL_LENGTHS};
+
+    /// The codes are complete, which checks the tables against typos.
+    #[test]
+    fn complete_codes() {
+        for (table, symbols) in [
+            (&LITERAL_LENGTHS[..], 256),
+            (&LENGTH_LENGTHS[..], 16),
+            (&DISTANCE_LENGTHS[..], 64),
+        ] {
+            let lengths = expand_lengths(table);
+            assert_eq!(lengths.len(), symbols);
+            let kraft: u32 = lengths.iter().map(|&length| 1 << (16 - length)).sum();
+            assert_eq!(kraft, 1 << 16);
+        }
+    }
+
+    #[test]
+    fn example() {
+        // The example used by zlib's blast
+        let compressed = [0x00, 0x04, 0x82, 0x24, 0x25, 0x8f, 0x80, 0x7f];
+        assert_eq!(explode(&compressed, 13).unwrap(), b"AIAIAIAIAIAIA");
+        assert_eq!(explode(&compressed, 100).unwrap(), b"AIAIAIAIAIAIA");
+    }
+}
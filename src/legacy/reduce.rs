@@ -0,0 +1,112 @@
+//! Reduce (methods 2 to 5, compression factors 1 to 4).
+//!
+//! Bytes are first coded with follower sets: each byte may be coded as an index into a small set
+//! of bytes that are likely to follow the previous byte. The resulting bytes are then expanded,
+//! with byte 144 (DLE) introducing a back-reference whose length and distance bits depend on the
+//! compression factor.
+
+use super::{copy_match, invalid_data, BitReader};
+use std::io;
+
+const DLE: u8 = 144;
+
+/// Number of bits needed to encode an index into a follower set of size `n`.
+fn index_bits(n: usize) -> u32 {
+    match n {
+        0..=2 => 1,
+        _ => usize::BITS - (n - 1).leading_zeros(),
+    }
+}
+
+enum State {
+    Literal,
+    Dle,
+    Length { v: u8, length: usize },
+    Distance { v: u8, length: usize },
+}
+
+/// Decompresses data compressed with the compression `factor`, from 1 to 4.
+pub(super) fn unreduce(compressed: &[u8], size: usize, factor: u8) -> io::Result<Vec<u8>> {
+    let length_mask = 0xffu8 >> factor;
+
+    let mut bits = BitReader::new(compressed);
+    let mut follower_sets = vec![Vec::new(); 256];
+    for set in follower_sets.iter_mut().rev() {
+        let count = bits.bits(6)?;
+        if count > 32 {
+            return Err(invalid_data("Invalid follower set size"));
+        }
+        for _ in 0..count {
+            set.push(bits.bits(8)? as u8);
+        }
+    }
+
+    let mut output = Vec::new();
+    let mut last_byte = 0u8;
+    let mut state = State::Literal;
+    while output.len() < size {
+        let set: &Vec<u8> = &follower_sets[last_byte as usize];
+        let byte = if set.is_empty() || bits.bits(1)? == 1 {
+            bits.bits(8)? as u8
+        } else {
+            let index = bits.bits(index_bits(set.len()))? as usize;
+            *set.get(index)
+                .ok_or_else(|| invalid_data("Invalid follower set index"))?
+        };
+        last_byte = byte;
+
+        state = match state {
+            State::Literal if byte == DLE => State::Dle,
+            State::Literal => {
+                output.push(byte);
+                State::Literal
+            }
+            State::Dle if byte == 0 => {
+                output.push(DLE);
+                State::Literal
+            }
+            State::Dle => {
+                let length = (byte & length_mask) as usize;
+                if byte & length_mask == length_mask {
+                    State::Length { v: byte, length }
+                } else {
+                    State::Distance { v: byte, length }
+                }
+            }
+            State::Length { v, length } => State::Distance {
+                v,
+                length: length + byte as usize,
+            },
+            State::Distance { v, length } => {
+                let distance = (((v >> (8 - factor)) as usize) << 8) + byte as usize + 1;
+                copy_match(&mut output, distance, length + 3, size);
+                State::Literal
+            }
+        };
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{index_bits, unreduce};
+
+    #[test]
+    fn follower_set_index_bits() {
+        let expected = [1, 1, 1, 2, 2, 3, 3, 3, 3, 4];
+        for (n, &bits) in expected.iter().enumerate() {
+            assert_eq!(index_bits(n), bits, "follower set of size {}", n);
+        }
+        assert_eq!(index_bits(32), 5);
+    }
+
+    #[test]
+    fn literals_and_match() {
+        // Empty follower sets, so every byte is 8 bits: "abc", then DLE with length 1 and
+        // distance 3 (4 bytes from 3 back), then an escaped DLE
+        let mut data = vec![0u8; 192];
+        data.extend_from_slice(b"abc");
+        data.extend_from_slice(&[144, 0x01, 0x02, 144, 0]);
+        assert_eq!(unreduce(&data, 8, 1).unwrap(), b"abcabca\x90");
+    }
+}
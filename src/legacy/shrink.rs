@@ -0,0 +1,184 @@
+//! Shrink (method 1): dynamic LZW with code sizes from 9 to 13 bits.
+//!
+//! Code 256 is a control code. It is followed by 1 to increase the code size, or by 2 to
+//! partially clear the table, which frees every code that isn't a prefix of another code. Freed
+//! codes are reused from the lowest one up.
+//!
+//! The string of each code is kept as the place it was last written to the output rather than as
+//! a chain of prefixes, as a partial clear may free the prefix of the next code to be added.
+
+use super::{invalid_data, BitReader};
+use std::io;
+
+const MIN_CODE_SIZE: u32 = 9;
+const MAX_CODE_SIZE: u32 = 13;
+const MAX_CODE: usize = (1 << MAX_CODE_SIZE) - 1;
+const CONTROL_CODE: usize = 256;
+const INCREASE_CODE_SIZE: u32 = 1;
+const PARTIAL_CLEAR: u32 = 2;
+
+/// Marks codes which aren't in use.
+const INVALID_CODE: u16 = u16::MAX;
+
+#[derive(Clone, Copy)]
+struct Entry {
+    /// Code of the string without its last byte, or `INVALID_CODE` if the code is free
+    prefix: u16,
+    /// Position of the string in the output
+    position: usize,
+    length: usize,
+}
+
+struct Table {
+    entries: Vec<Entry>,
+    /// Lowest code above the control code which may be free
+    next_free: usize,
+}
+
+impl Table {
+    fn new() -> Table {
+        let mut entries = vec![
+            Entry {
+                prefix: INVALID_CODE,
+                position: 0,
+                length: 0,
+            };
+            MAX_CODE + 1
+        ];
+        for (byte, entry) in entries.iter_mut().enumerate().take(CONTROL_CODE) {
+            // Literals have no prefix, but must not look free
+            entry.prefix = byte as u16;
+            entry.length = 1;
+        }
+        Table {
+            entries,
+            next_free: CONTROL_CODE + 1,
+        }
+    }
+
+    fn is_valid(&self, code: usize) -> bool {
+        code < CONTROL_CODE
+            || (code > CONTROL_CODE
+                && code <= MAX_CODE
+                && self.entries[code].prefix != INVALID_CODE)
+    }
+
+    /// Returns the code the next string will be added with, if the table isn't full.
+    fn free_code(&mut self) -> Option<usize> {
+        while self.next_free <= MAX_CODE && self.entries[self.next_free].prefix != INVALID_CODE {
+            self.next_free += 1;
+        }
+        if self.next_free <= MAX_CODE {
+            Some(self.next_free)
+        } else {
+            None
+        }
+    }
+
+    fn partial_clear(&mut self) {
+        let mut is_prefix = vec![false; MAX_CODE + 1];
+        for entry in &self.entries[CONTROL_CODE + 1..] {
+            if entry.prefix != INVALID_CODE {
+                is_prefix[entry.prefix as usize] = true;
+            }
+        }
+        for (entry, &is_prefix) in self
+            .entries
+            .iter_mut()
+            .zip(&is_prefix)
+            .skip(CONTROL_CODE + 1)
+        {
+            if !is_prefix {
+                entry.prefix = INVALID_CODE;
+            }
+        }
+        self.next_free = CONTROL_CODE + 1;
+    }
+}
+
+pub(super) fn unshrink(compressed: &[u8], size: usize) -> io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    if size == 0 {
+        return Ok(output);
+    }
+
+    let mut bits = BitReader::new(compressed);
+    let mut table = Table::new();
+    let mut code_size = MIN_CODE_SIZE;
+
+    let mut previous = bits.bits(code_size)? as usize;
+    if previous >= CONTROL_CODE {
+        return Err(invalid_data("Invalid first code in shrunk data"));
+    }
+    output.push(previous as u8);
+
+    while output.len() < size {
+        let code = bits.bits(code_size)? as usize;
+        if code == CONTROL_CODE {
+            match bits.bits(code_size)? {
+                INCREASE_CODE_SIZE if code_size < MAX_CODE_SIZE => code_size += 1,
+                PARTIAL_CLEAR => table.partial_clear(),
+                _ => return Err(invalid_data("Invalid control code in shrunk data")),
+            }
+            continue;
+        }
+
+        let free_code = table.free_code();
+        let start = output.len();
+        if code < CONTROL_CODE {
+            output.push(code as u8);
+        } else if table.is_valid(code) {
+            let entry = table.entries[code];
+            output.extend_from_within(entry.position..entry.position + entry.length);
+        } else if Some(code) == free_code && table.is_valid(previous) {
+            // The code is the one about to be added: the previous string followed by its own
+            // first byte.
+            let entry = table.entries[previous];
+            output.extend_from_within(entry.position..entry.position + entry.length);
+            output.push(output[start]);
+        } else {
+            return Err(invalid_data("Invalid code in shrunk data"));
+        }
+
+        // The previous string was written just before this one, so together with the first
+        // byte of this one, it forms the new string.
+        if let Some(free_code) = free_code {
+            let entry = table.entries[previous];
+            table.entries[free_code] = Entry {
+                prefix: previous as u16,
+                position: entry.position,
+                length: entry.length + 1,
+            };
+        }
+        table.entries[code].position = start;
+        previous = code;
+    }
+
+    output.truncate(size);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::unshrink;
+
+    #[test]
+    fn kwkwk() {
+        // 'a', then code 257 ("aa") before it has been added, then 257 again
+        let codes = [b'a' as u32, 257, 257];
+        let mut data = Vec::new();
+        let mut buffer = 0u32;
+        let mut count = 0;
+        for &code in &codes {
+            buffer |= code << count;
+            count += 9;
+            while count >= 8 {
+                data.push(buffer as u8);
+                buffer >>= 8;
+                count -= 8;
+            }
+        }
+        data.push(buffer as u8);
+        assert_eq!(unshrink(&data, 5).unwrap(), b"aaaaa");
+    }
+}
@@ -14,12 +14,29 @@ pub use crate::write::ZipWriter;
 mod aes;
 #[cfg(feature = "aes-crypto")]
 mod aes_ctr;
+mod checkpoint;
+pub mod codec;
 mod compression;
 mod cp437;
 mod crc32;
+mod encoding;
+pub mod extra_fields;
+mod extract;
+#[cfg(feature = "legacy")]
+mod legacy;
+mod limits;
+#[cfg(feature = "lzma")]
+mod lzma;
+#[cfg(feature = "ppmd")]
+mod ppmd;
 pub mod read;
+mod read_at;
+mod read_options;
+mod recover;
+mod repair;
 pub mod result;
 mod spec;
 mod types;
+mod verify;
 pub mod write;
 mod zipcrypto;
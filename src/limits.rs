@@ -0,0 +1,244 @@
+//! Limits on the resources an archive may use, to protect against zip bombs
+
+use crate::read::ZipFile;
+use crate::result::{ZipError, ZipResult};
+use crate::types::ZipFileData;
+#[cfg(any(feature = "lzma", feature = "xz"))]
+use std::io;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Limits on the size of an archive and its contents
+///
+/// Archives can be crafted to decompress to far more data than they take up, or to declare more
+/// files than could be processed. Limits guard against this when reading untrusted archives with
+/// [`ReadOptions::limits`](crate::read::ReadOptions::limits). Exceeding a limit fails with
+/// [`ZipError::LimitExceeded`].
+///
+/// The number of files, the size of the central directory and the total size declared by the
+/// files are checked when opening the archive. The size and compression ratio of a file are
+/// checked when it is opened and again while its data is decompressed, since the declared sizes
+/// can't be trusted. When extracting, the total size of the extracted data is checked as well.
+///
+/// Optionally, archives whose files overlap each other are rejected, see
+/// [`Limits::reject_overlapping_files`].
+///
+/// Files compressed with Shrink, Reduce or Implode are decompressed into memory at once when they
+/// are first read, so all of their compressed and uncompressed data is held in memory while they
+/// are read. [`Limits::max_file_size`] bounds the uncompressed data. The memory that LZMA, XZ and
+/// PPMd compressed files need is bounded by [`Limits::max_decompression_memory`].
+///
+/// No limits are set by default.
+///
+/// ```no_run
+/// use zip::read::{Limits, ReadOptions};
+///
+/// fn open(file: std::fs::File) -> zip::result::ZipResult<zip::ZipArchive<std::fs::File>> {
+///     let limits = Limits::default()
+///         .max_files(10_000)
+///         .max_file_size(1 << 30)
+///         .max_total_size(4 << 30)
+///         .max_compression_ratio(100);
+///     zip::ZipArchive::with_options(file, ReadOptions::default().limits(limits))
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Limits {
+    max_total_size: Option<u64>,
+    max_file_size: Option<u64>,
+    max_compression_ratio: Option<u64>,
+    max_files: Option<usize>,
+    max_central_directory_size: Option<u64>,
+    max_decompression_memory: Option<u64>,
+    reject_overlapping_files: bool,
+}
+
+impl Limits {
+    /// Set the maximum total uncompressed size of all files, in bytes
+    #[must_use]
+    pub fn max_total_size(mut self, size: u64) -> Limits {
+        self.max_total_size = Some(size);
+        self
+    }
+
+    /// Set the maximum uncompressed size of a single file, in bytes
+    #[must_use]
+    pub fn max_file_size(mut self, size: u64) -> Limits {
+        self.max_file_size = Some(size);
+        self
+    }
+
+    /// Set the maximum ratio of the uncompressed size of a file to its compressed size
+    #[must_use]
+    pub fn max_compression_ratio(mut self, ratio: u64) -> Limits {
+        self.max_compression_ratio = Some(ratio);
+        self
+    }
+
+    /// Set the maximum number of files in the archive
+    #[must_use]
+    pub fn max_files(mut self, count: usize) -> Limits {
+        self.max_files = Some(count);
+        self
+    }
+
+    /// Set the maximum size of the central directory, in bytes
+    #[must_use]
+    pub fn max_central_directory_size(mut self, size: u64) -> Limits {
+        self.max_central_directory_size = Some(size);
+        self
+    }
+
+    /// Set the maximum memory that decompressing a file may allocate up front, in bytes
+    ///
+    /// PPMd compressed files store the size of their model at the start of the compressed data,
+    /// and it is allocated when the file is first read. The size can be up to 256 MiB. LZMA
+    /// compressed files store the size of their dictionary in the same way, which can be up to
+    /// 4 GiB, and XZ compressed files in the header of every block.
+    #[must_use]
+    pub fn max_decompression_memory(mut self, size: u64) -> Limits {
+        self.max_decompression_memory = Some(size);
+        self
+    }
+
+    /// Set whether to reject archives in which files overlap each other or the central
+    /// directory, with [`ZipError::InvalidArchive`].
+    ///
+    /// Some zip bombs reuse the same compressed data for many files, so that the archive
+    /// decompresses to much more data than it could hold otherwise. This reads the local header
+    /// of every file when the archive is opened. The default is `false`. See
+    /// [`ZipArchive::find_overlaps`](crate::ZipArchive::find_overlaps).
+    #[must_use]
+    pub fn reject_overlapping_files(mut self, reject: bool) -> Limits {
+        self.reject_overlapping_files = reject;
+        self
+    }
+
+    pub(crate) fn rejects_overlapping_files(&self) -> bool {
+        self.reject_overlapping_files
+    }
+
+    pub(crate) fn check_files(&self, count: usize) -> ZipResult<()> {
+        check(count, self.max_files, "Too many files")
+    }
+
+    pub(crate) fn check_central_directory_size(&self, size: u64) -> ZipResult<()> {
+        check(
+            size,
+            self.max_central_directory_size,
+            "Central directory too large",
+        )
+    }
+
+    pub(crate) fn check_total_size(&self, size: u64) -> ZipResult<()> {
+        check(size, self.max_total_size, "Total size of files too large")
+    }
+
+    /// Check the files found by scanning an archive, as the central directory is checked when
+    /// opening it.
+    pub(crate) fn check_scanned_files(&self, files: &[ZipFileData]) -> ZipResult<()> {
+        self.check_files(files.len())?;
+        let total_size = files.iter().fold(0u64, |total, data| {
+            total.saturating_add(data.uncompressed_size)
+        });
+        self.check_total_size(total_size)
+    }
+
+    /// Copy an extracted file, adding its size to the total size of the files extracted so far.
+    pub(crate) fn copy_file(
+        &self,
+        file: &mut ZipFile,
+        output: &mut impl Write,
+        total_size: &AtomicU64,
+    ) -> ZipResult<()> {
+        let limit = match self.max_total_size {
+            // Copy one more byte than allowed, to find out whether the limit is exceeded
+            Some(max) => max
+                .saturating_sub(total_size.load(Ordering::Relaxed))
+                .saturating_add(1),
+            None => u64::MAX,
+        };
+        let count = file.copy_to(output, limit)?;
+        let total = total_size.fetch_add(count, Ordering::Relaxed) + count;
+        self.check_total_size(total)
+    }
+
+    #[cfg(feature = "ppmd")]
+    pub(crate) fn check_decompression_memory(&self, size: u64) -> ZipResult<()> {
+        check(size, self.max_decompression_memory, MEMORY_EXCEEDED)
+    }
+
+    /// The memory limit for liblzma, which checks it itself while decoding the headers.
+    #[cfg(any(feature = "lzma", feature = "xz"))]
+    pub(crate) fn decompression_memory_limit(&self) -> u64 {
+        self.max_decompression_memory.unwrap_or(u64::MAX)
+    }
+
+    /// Check that `size` bytes of uncompressed data are allowed for a file.
+    pub(crate) fn check_file_size(&self, data: &ZipFileData, size: u64) -> ZipResult<()> {
+        check(size, self.max_file_size, "File too large")?;
+        let max_size = self
+            .max_compression_ratio
+            .map(|ratio| data.compressed_size.max(1).saturating_mul(ratio));
+        check(size, max_size, "Compression ratio too high")
+    }
+}
+
+#[cfg(any(feature = "lzma", feature = "xz", feature = "ppmd"))]
+const MEMORY_EXCEEDED: &str = "Decompression needs too much memory";
+
+/// Report liblzma exceeding [`Limits::decompression_memory_limit`] as an exceeded limit.
+#[cfg(any(feature = "lzma", feature = "xz"))]
+pub(crate) fn from_lzma_error(err: io::Error) -> ZipError {
+    match err.get_ref().and_then(|e| e.downcast_ref()) {
+        Some(xz2::stream::Error::MemLimit) => ZipError::LimitExceeded(MEMORY_EXCEEDED),
+        _ => ZipError::Io(err),
+    }
+}
+
+fn check<T: PartialOrd>(value: T, limit: Option<T>, detail: &'static str) -> ZipResult<()> {
+    match limit {
+        Some(limit) if value > limit => Err(ZipError::LimitExceeded(detail)),
+        _ => Ok(()),
+    }
+}
+
+/// A file that overlaps with another part of the archive, found by
+/// [`ZipArchive::find_overlaps`](crate::ZipArchive::find_overlaps)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Overlap {
+    /// The file with the first index overlaps the file with the second index, which starts
+    /// before it
+    File(usize, usize),
+    /// The file with this index extends into the central directory
+    CentralDirectory(usize),
+}
+
+/// Find the files whose local header or data overlap each other or the central directory. The
+/// start of the data must have been found for all files.
+pub(crate) fn find_overlaps(files: &[ZipFileData], directory_start: u64) -> Vec<Overlap> {
+    let mut order: Vec<usize> = (0..files.len()).collect();
+    order.sort_by_key(|&i| files[i].header_start);
+
+    let mut overlaps = Vec::new();
+    // The end of the file that extends furthest so far, and its index
+    let mut furthest: Option<(u64, usize)> = None;
+    for i in order {
+        let file = &files[i];
+        let end = file.data_start.load().saturating_add(file.compressed_size);
+        match furthest {
+            Some((furthest_end, other)) if file.header_start < furthest_end => {
+                overlaps.push(Overlap::File(i, other));
+            }
+            _ => {}
+        }
+        if end > directory_start {
+            overlaps.push(Overlap::CentralDirectory(i));
+        }
+        match furthest {
+            Some((furthest_end, _)) if furthest_end >= end => {}
+            _ => furthest = Some((end, i)),
+        }
+    }
+    overlaps
+}
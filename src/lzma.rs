@@ -0,0 +1,211 @@
+//! Implementation of LZMA compression (method 14) for zip files.
+//!
+//! A zip LZMA entry starts with a header of its own: two bytes of LZMA SDK version, the two byte
+//! length of the properties and the five bytes of properties themselves. It is followed by a raw
+//! LZMA stream, which ends with an end-of-stream marker if bit 1 of the general purpose flag is
+//! set. liblzma only reads and writes the `.lzma` format, whose header holds the same properties
+//! followed by the uncompressed size, so the reader and writer here translate between the two.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+use xz2::stream::{LzmaOptions, Stream};
+
+/// The length of the LZMA properties in bytes
+const PROPERTIES_LENGTH: usize = 5;
+/// The length of the uncompressed size in the `.lzma` header in bytes
+const SIZE_LENGTH: usize = 8;
+/// The LZMA SDK version written to the header. Readers ignore it.
+const VERSION: [u8; 2] = [5, 2];
+
+type LzmaHeader = io::Cursor<[u8; PROPERTIES_LENGTH + SIZE_LENGTH]>;
+
+enum LzmaReaderState<R: Read> {
+    Header(R),
+    Decoding(Box<xz2::read::XzDecoder<io::Chain<LzmaHeader, R>>>),
+    Invalid,
+}
+
+/// Reader for the LZMA compressed data of a zip file.
+///
+/// The header is read on the first read, so that the underlying reader can still be retrieved
+/// if it is invalid.
+pub struct LzmaReader<R: Read> {
+    state: LzmaReaderState<R>,
+    uncompressed_size: u64,
+    memory_limit: u64,
+}
+
+impl<R: Read> LzmaReader<R> {
+    /// Create a reader whose decoder fails if the dictionary needs more than `memory_limit`
+    /// bytes.
+    pub fn new(reader: R, uncompressed_size: u64, memory_limit: u64) -> LzmaReader<R> {
+        LzmaReader {
+            state: LzmaReaderState::Header(reader),
+            uncompressed_size,
+            memory_limit,
+        }
+    }
+
+    fn start_decoding(&mut self) -> io::Result<()> {
+        let mut reader = match std::mem::replace(&mut self.state, LzmaReaderState::Invalid) {
+            LzmaReaderState::Header(reader) => reader,
+            state => {
+                self.state = state;
+                return Ok(());
+            }
+        };
+
+        let header = read_header(&mut reader);
+        let header = match header {
+            Ok(properties) => {
+                // The decoder stops at the uncompressed size, so it works both with and
+                // without an end-of-stream marker.
+                let mut header = [0; PROPERTIES_LENGTH + SIZE_LENGTH];
+                header[..PROPERTIES_LENGTH].copy_from_slice(&properties);
+                header[PROPERTIES_LENGTH..].copy_from_slice(&self.uncompressed_size.to_le_bytes());
+                header
+            }
+            Err(e) => {
+                self.state = LzmaReaderState::Header(reader);
+                return Err(e);
+            }
+        };
+
+        let stream = Stream::new_lzma_decoder(self.memory_limit)?;
+        self.state = LzmaReaderState::Decoding(Box::new(xz2::read::XzDecoder::new_stream(
+            io::Cursor::new(header).chain(reader),
+            stream,
+        )));
+        Ok(())
+    }
+
+    /// Consumes this decoder, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        match self.state {
+            LzmaReaderState::Header(reader) => reader,
+            LzmaReaderState::Decoding(decoder) => decoder.into_inner().into_inner().1,
+            LzmaReaderState::Invalid => panic!("LzmaReader was in an invalid state"),
+        }
+    }
+}
+
+fn read_header<R: Read>(reader: &mut R) -> io::Result<[u8; PROPERTIES_LENGTH]> {
+    let _version = reader.read_u16::<LittleEndian>()?;
+    let properties_length = reader.read_u16::<LittleEndian>()?;
+    if properties_length as usize != PROPERTIES_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Unsupported LZMA properties length",
+        ));
+    }
+    let mut properties = [0; PROPERTIES_LENGTH];
+    reader.read_exact(&mut properties)?;
+    Ok(properties)
+}
+
+impl<R: Read> Read for LzmaReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.start_decoding()?;
+        match &mut self.state {
+            LzmaReaderState::Decoding(decoder) => decoder.read(buf),
+            _ => panic!("LzmaReader was in an invalid state"),
+        }
+    }
+}
+
+/// Writer that replaces the `.lzma` header written by liblzma with the zip LZMA header.
+pub struct LzmaHeaderWriter<W> {
+    inner: W,
+    header_position: usize,
+}
+
+impl<W: Write> Write for LzmaHeaderWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        const HEADER_LENGTH: usize = PROPERTIES_LENGTH + SIZE_LENGTH;
+
+        if self.header_position >= HEADER_LENGTH {
+            return self.inner.write(buf);
+        }
+
+        let count = buf.len().min(HEADER_LENGTH - self.header_position);
+        for &byte in &buf[..count] {
+            if self.header_position == 0 {
+                self.inner.write_all(&VERSION)?;
+                self.inner
+                    .write_u16::<LittleEndian>(PROPERTIES_LENGTH as u16)?;
+            }
+            if self.header_position < PROPERTIES_LENGTH {
+                self.inner.write_all(&[byte])?;
+            }
+            self.header_position += 1;
+        }
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writer for LZMA compressed data of a zip file, always ending with an end-of-stream marker.
+pub struct LzmaWriter<W: Write> {
+    inner: xz2::write::XzEncoder<LzmaHeaderWriter<W>>,
+}
+
+impl<W: Write> LzmaWriter<W> {
+    pub fn new(inner: W) -> io::Result<LzmaWriter<W>> {
+        let options = LzmaOptions::new_preset(6)?;
+        let stream = Stream::new_lzma_encoder(&options)?;
+        let header_writer = LzmaHeaderWriter {
+            inner,
+            header_position: 0,
+        };
+        Ok(LzmaWriter {
+            inner: xz2::write::XzEncoder::new_stream(header_writer, stream),
+        })
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        Ok(self.inner.finish()?.inner)
+    }
+}
+
+impl<W: Write> Write for LzmaWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LzmaReader, LzmaWriter};
+    use std::io::{Read, Write};
+
+    #[test]
+    fn roundtrip() {
+        let data = b"Lorem ipsum dolor sit amet, lorem ipsum dolor sit amet.".repeat(100);
+
+        let mut writer = LzmaWriter::new(Vec::new()).unwrap();
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+        assert_eq!(&compressed[2..9], &[5, 0, 0x5d, 0, 0, 0x80, 0]);
+        assert!(compressed.len() < data.len());
+
+        let mut reader = LzmaReader::new(compressed.as_slice(), data.len() as u64, u64::MAX);
+        let mut decompressed = Vec::new();
+        reader.read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn invalid_properties_length() {
+        let compressed = [5, 2, 4, 0, 0x5d, 0, 0, 0x80];
+        let mut reader = LzmaReader::new(&compressed[..], 0, u64::MAX);
+        assert!(reader.read(&mut [0; 16]).is_err());
+        assert_eq!(reader.into_inner(), &compressed[4..]);
+    }
+}
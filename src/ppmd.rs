@@ -0,0 +1,216 @@
+//! Implementation of PPMd compression (method 98) for zip files.
+//!
+//! Zip files use PPMd variant I revision 1. The compressed data starts with a little endian
+//! 16-bit word holding the model order minus one in bits 0-3, the memory size in MiB minus one
+//! in bits 4-11 and the model restoration method in bits 12-15.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use ppmd_rust::{Ppmd8Decoder, Ppmd8Encoder, RestoreMethod};
+use std::io::{self, Read, Write};
+
+/// The model order used when compressing
+const ORDER: u32 = 6;
+/// The memory size in MiB used when compressing
+const MEMORY_SIZE_MIB: u32 = 16;
+
+fn ppmd_error(error: ppmd_rust::Error) -> io::Error {
+    match error {
+        ppmd_rust::Error::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+    }
+}
+
+/// The parameters stored at the start of the compressed data
+#[derive(Copy, Clone)]
+struct Parameters {
+    order: u32,
+    memory_size: u32,
+    restore_method: RestoreMethod,
+}
+
+enum PpmdReaderState<R: Read> {
+    Header(R),
+    Parsed(R, Parameters),
+    Decoding(Box<io::Take<Ppmd8Decoder<R>>>, Parameters),
+    Invalid,
+}
+
+/// Reader for the PPMd compressed data of a zip file.
+///
+/// The header is read on the first read, so that the underlying reader can still be retrieved
+/// if it is invalid. The data doesn't need to contain an end marker, decoding stops at the
+/// uncompressed size.
+pub struct PpmdReader<R: Read> {
+    state: PpmdReaderState<R>,
+    uncompressed_size: u64,
+}
+
+impl<R: Read> PpmdReader<R> {
+    pub fn new(reader: R, uncompressed_size: u64) -> PpmdReader<R> {
+        PpmdReader {
+            state: PpmdReaderState::Header(reader),
+            uncompressed_size,
+        }
+    }
+
+    /// Get the size of the model in bytes, which is allocated when decoding starts. This reads
+    /// the header if it hasn't been read yet.
+    pub fn memory_size(&mut self) -> io::Result<u32> {
+        self.read_header()?;
+        match &self.state {
+            PpmdReaderState::Parsed(_, parameters) | PpmdReaderState::Decoding(_, parameters) => {
+                Ok(parameters.memory_size)
+            }
+            _ => Err(invalid_state()),
+        }
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let mut reader = match std::mem::replace(&mut self.state, PpmdReaderState::Invalid) {
+            PpmdReaderState::Header(reader) => reader,
+            state => {
+                self.state = state;
+                return Ok(());
+            }
+        };
+
+        let parameters = match reader.read_u16::<LittleEndian>() {
+            Ok(parameters) => parameters,
+            Err(e) => {
+                self.state = PpmdReaderState::Header(reader);
+                return Err(e);
+            }
+        };
+        let restore_method = match parameters >> 12 {
+            0 => RestoreMethod::Restart,
+            1 => RestoreMethod::CutOff,
+            _ => {
+                self.state = PpmdReaderState::Header(reader);
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Unsupported PPMd restore method",
+                ));
+            }
+        };
+        let parameters = Parameters {
+            order: (parameters & 0xf) as u32 + 1,
+            memory_size: (((parameters >> 4) & 0xff) as u32 + 1) << 20,
+            restore_method,
+        };
+        self.state = PpmdReaderState::Parsed(reader, parameters);
+        Ok(())
+    }
+
+    fn start_decoding(&mut self) -> io::Result<()> {
+        self.read_header()?;
+        let (reader, parameters) =
+            match std::mem::replace(&mut self.state, PpmdReaderState::Invalid) {
+                PpmdReaderState::Parsed(reader, parameters) => (reader, parameters),
+                state => {
+                    self.state = state;
+                    return Ok(());
+                }
+            };
+
+        let decoder = Ppmd8Decoder::new(
+            reader,
+            parameters.order,
+            parameters.memory_size,
+            parameters.restore_method,
+        )
+        .map_err(ppmd_error)?;
+        self.state =
+            PpmdReaderState::Decoding(Box::new(decoder.take(self.uncompressed_size)), parameters);
+        Ok(())
+    }
+
+    /// Consumes this decoder, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        match self.state {
+            PpmdReaderState::Header(reader) => reader,
+            PpmdReaderState::Parsed(reader, _) => reader,
+            PpmdReaderState::Decoding(decoder, _) => decoder.into_inner().into_inner(),
+            PpmdReaderState::Invalid => panic!("PpmdReader was in an invalid state"),
+        }
+    }
+}
+
+fn invalid_state() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "PPMd decoder could not be created")
+}
+
+impl<R: Read> Read for PpmdReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.start_decoding()?;
+        match &mut self.state {
+            PpmdReaderState::Decoding(decoder, _) => decoder.read(buf),
+            // Creating the decoder failed
+            _ => Err(invalid_state()),
+        }
+    }
+}
+
+/// Writer for PPMd compressed data of a zip file, ending with an end marker.
+pub struct PpmdWriter<W: Write> {
+    inner: Box<Ppmd8Encoder<W>>,
+}
+
+impl<W: Write> PpmdWriter<W> {
+    pub fn new(mut inner: W) -> io::Result<PpmdWriter<W>> {
+        let restore_method = RestoreMethod::Restart;
+        let parameters =
+            (ORDER - 1) | ((MEMORY_SIZE_MIB - 1) << 4) | ((restore_method as u32) << 12);
+        inner.write_u16::<LittleEndian>(parameters as u16)?;
+        let encoder = Ppmd8Encoder::new(inner, ORDER, MEMORY_SIZE_MIB << 20, restore_method)
+            .map_err(ppmd_error)?;
+        Ok(PpmdWriter {
+            inner: Box::new(encoder),
+        })
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        self.inner.finish(true)
+    }
+}
+
+impl<W: Write> Write for PpmdWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Flushing the encoder would flush the range coder and corrupt the stream
+        self.inner.get_mut().flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PpmdReader, PpmdWriter};
+    use std::io::{Read, Write};
+
+    #[test]
+    fn roundtrip() {
+        let data = b"Lorem ipsum dolor sit amet, lorem ipsum dolor sit amet.".repeat(100);
+
+        let mut writer = PpmdWriter::new(Vec::new()).unwrap();
+        writer.write_all(&data).unwrap();
+        let compressed = writer.finish().unwrap();
+        // Order 6, 16 MiB and the restart method
+        assert_eq!(&compressed[..2], &[0xf5, 0x00]);
+        assert!(compressed.len() < data.len());
+
+        let mut reader = PpmdReader::new(compressed.as_slice(), data.len() as u64);
+        let mut decompressed = Vec::new();
+        reader.read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[test]
+    fn invalid_restore_method() {
+        let compressed = [0xf5, 0x20, 0, 0, 0, 0];
+        let mut reader = PpmdReader::new(&compressed[..], 0);
+        assert!(reader.read(&mut [0; 16]).is_err());
+        assert_eq!(reader.into_inner(), &compressed[2..]);
+    }
+}
@@ -2,20 +2,39 @@
 
 #[cfg(feature = "aes-crypto")]
 use crate::aes::{AesReader, AesReaderValid};
+use crate::codec::{Codec, CodecReader, Codecs};
 use crate::compression::CompressionMethod;
-use crate::cp437::FromCp437;
 use crate::crc32::Crc32Reader;
+use crate::extra_fields::{ExtraField, ExtraFields};
+#[cfg(feature = "legacy")]
+use crate::legacy::LegacyReader;
+#[cfg(feature = "lzma")]
+use crate::lzma::LzmaReader;
+#[cfg(feature = "ppmd")]
+use crate::ppmd::PpmdReader;
 use crate::result::{InvalidPassword, ZipError, ZipResult};
 use crate::spec;
 use crate::types::{AesMode, AesVendorVersion, AtomicU64, DateTime, System, ZipFileData};
 use crate::zipcrypto::{ZipCryptoReader, ZipCryptoReaderValid, ZipCryptoValidator};
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::io::{self, prelude::*};
-use std::path::{Component, Path};
+use std::path::Path;
 use std::sync::Arc;
 
+pub use crate::checkpoint::{CheckpointIndex, CheckpointedZipFile};
+#[cfg(feature = "encoding_rs")]
+pub use crate::encoding::DetectEncoding;
+pub use crate::encoding::{Cp437, NameDecoder};
+pub use crate::extract::{ExtractOptions, Overwrite};
+pub use crate::limits::{Limits, Overlap};
+pub use crate::read_at::{ReadAt, ReadAtCursor};
+pub use crate::read_options::ReadOptions;
+pub use crate::repair::RepairReport;
+pub use crate::verify::{FileReport, VerifyProblem, VerifyReport};
+
 #[cfg(any(
     feature = "deflate",
     feature = "deflate-miniz",
@@ -23,23 +42,27 @@ use std::sync::Arc;
 ))]
 use flate2::read::DeflateDecoder;
 
+#[cfg(feature = "deflate64")]
+use deflate64::Deflate64Decoder;
+
 #[cfg(feature = "bzip2")]
 use bzip2::read::BzDecoder;
 
+#[cfg(feature = "xz")]
+use xz2::{read::XzDecoder, stream::Stream};
+
+#[cfg(feature = "zstd")]
+use zstd::dict::DecoderDictionary;
 #[cfg(feature = "zstd")]
 use zstd::stream::read::Decoder as ZstdDecoder;
 
-mod ffi {
-    pub const S_IFDIR: u32 = 0o0040000;
-    pub const S_IFREG: u32 = 0o0100000;
-}
-
 /// Extract immutable data from `ZipArchive` to make it cheap to clone
 #[derive(Debug)]
 struct Shared {
     files: Vec<ZipFileData>,
     names_map: HashMap<String, usize>,
     offset: u64,
+    directory_start: u64,
     comment: Vec<u8>,
 }
 
@@ -67,15 +90,43 @@ struct Shared {
 pub struct ZipArchive<R> {
     reader: R,
     shared: Arc<Shared>,
+    codecs: Codecs,
+    #[cfg(feature = "zstd")]
+    zstd_dictionaries: ZstdDictionaries,
+    limits: Limits,
+    read_local_extra_fields: bool,
+}
+
+/// Prepared Zstandard dictionaries, keyed by their dictionary ID
+#[cfg(feature = "zstd")]
+#[derive(Clone, Default)]
+pub(crate) struct ZstdDictionaries(HashMap<u32, Arc<DecoderDictionary<'static>>>);
+
+#[cfg(feature = "zstd")]
+impl ZstdDictionaries {
+    /// Find the dictionary referenced by the frame header at the start of `data`.
+    ///
+    /// Frames that don't reference a dictionary by ID use the dictionary without an ID, if any.
+    pub(crate) fn for_frame(&self, data: &[u8]) -> Option<&Arc<DecoderDictionary<'static>>> {
+        let id = zstd::zstd_safe::get_dict_id_from_frame(data);
+        self.0.get(&id)
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl std::fmt::Debug for ZstdDictionaries {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
 }
 
 #[allow(clippy::large_enum_variant)]
 enum CryptoReader<'a> {
-    Plaintext(io::Take<&'a mut dyn Read>),
-    ZipCrypto(ZipCryptoReaderValid<io::Take<&'a mut dyn Read>>),
+    Plaintext(io::Take<Box<dyn Read + 'a>>),
+    ZipCrypto(ZipCryptoReaderValid<io::Take<Box<dyn Read + 'a>>>),
     #[cfg(feature = "aes-crypto")]
     Aes {
-        reader: AesReaderValid<io::Take<&'a mut dyn Read>>,
+        reader: AesReaderValid<io::Take<Box<dyn Read + 'a>>>,
         vendor_version: AesVendorVersion,
     },
 }
@@ -93,7 +144,7 @@ impl<'a> Read for CryptoReader<'a> {
 
 impl<'a> CryptoReader<'a> {
     /// Consumes this decoder, returning the underlying reader.
-    pub fn into_inner(self) -> io::Take<&'a mut dyn Read> {
+    pub fn into_inner(self) -> io::Take<Box<dyn Read + 'a>> {
         match self {
             CryptoReader::Plaintext(r) => r,
             CryptoReader::ZipCrypto(r) => r.into_inner(),
@@ -119,18 +170,29 @@ impl<'a> CryptoReader<'a> {
 
 enum ZipFileReader<'a> {
     NoReader,
-    Raw(io::Take<&'a mut dyn io::Read>),
+    Raw(io::Take<Box<dyn Read + 'a>>),
     Stored(Crc32Reader<CryptoReader<'a>>),
+    #[cfg(feature = "legacy")]
+    Legacy(Crc32Reader<LegacyReader<CryptoReader<'a>>>),
     #[cfg(any(
         feature = "deflate",
         feature = "deflate-miniz",
         feature = "deflate-zlib"
     ))]
     Deflated(Crc32Reader<flate2::read::DeflateDecoder<CryptoReader<'a>>>),
+    #[cfg(feature = "deflate64")]
+    Deflate64(Crc32Reader<Deflate64Decoder<io::BufReader<CryptoReader<'a>>>>),
     #[cfg(feature = "bzip2")]
     Bzip2(Crc32Reader<BzDecoder<CryptoReader<'a>>>),
+    #[cfg(feature = "lzma")]
+    Lzma(Crc32Reader<LzmaReader<CryptoReader<'a>>>),
     #[cfg(feature = "zstd")]
     Zstd(Crc32Reader<ZstdDecoder<'a, io::BufReader<CryptoReader<'a>>>>),
+    #[cfg(feature = "xz")]
+    Xz(Crc32Reader<XzDecoder<CryptoReader<'a>>>),
+    #[cfg(feature = "ppmd")]
+    Ppmd(Crc32Reader<PpmdReader<CryptoReader<'a>>>),
+    Custom(Crc32Reader<CodecReader<CryptoReader<'a>>>),
 }
 
 impl<'a> Read for ZipFileReader<'a> {
@@ -139,37 +201,59 @@ impl<'a> Read for ZipFileReader<'a> {
             ZipFileReader::NoReader => panic!("ZipFileReader was in an invalid state"),
             ZipFileReader::Raw(r) => r.read(buf),
             ZipFileReader::Stored(r) => r.read(buf),
+            #[cfg(feature = "legacy")]
+            ZipFileReader::Legacy(r) => r.read(buf),
             #[cfg(any(
                 feature = "deflate",
                 feature = "deflate-miniz",
                 feature = "deflate-zlib"
             ))]
             ZipFileReader::Deflated(r) => r.read(buf),
+            #[cfg(feature = "deflate64")]
+            ZipFileReader::Deflate64(r) => r.read(buf),
             #[cfg(feature = "bzip2")]
             ZipFileReader::Bzip2(r) => r.read(buf),
+            #[cfg(feature = "lzma")]
+            ZipFileReader::Lzma(r) => r.read(buf),
             #[cfg(feature = "zstd")]
             ZipFileReader::Zstd(r) => r.read(buf),
+            #[cfg(feature = "xz")]
+            ZipFileReader::Xz(r) => r.read(buf),
+            #[cfg(feature = "ppmd")]
+            ZipFileReader::Ppmd(r) => r.read(buf),
+            ZipFileReader::Custom(r) => r.read(buf),
         }
     }
 }
 
 impl<'a> ZipFileReader<'a> {
     /// Consumes this decoder, returning the underlying reader.
-    pub fn into_inner(self) -> io::Take<&'a mut dyn Read> {
+    pub fn into_inner(self) -> io::Take<Box<dyn Read + 'a>> {
         match self {
             ZipFileReader::NoReader => panic!("ZipFileReader was in an invalid state"),
             ZipFileReader::Raw(r) => r,
             ZipFileReader::Stored(r) => r.into_inner().into_inner(),
+            #[cfg(feature = "legacy")]
+            ZipFileReader::Legacy(r) => r.into_inner().into_inner().into_inner(),
             #[cfg(any(
                 feature = "deflate",
                 feature = "deflate-miniz",
                 feature = "deflate-zlib"
             ))]
             ZipFileReader::Deflated(r) => r.into_inner().into_inner().into_inner(),
+            #[cfg(feature = "deflate64")]
+            ZipFileReader::Deflate64(r) => r.into_inner().into_inner().into_inner().into_inner(),
             #[cfg(feature = "bzip2")]
             ZipFileReader::Bzip2(r) => r.into_inner().into_inner().into_inner(),
+            #[cfg(feature = "lzma")]
+            ZipFileReader::Lzma(r) => r.into_inner().into_inner().into_inner(),
             #[cfg(feature = "zstd")]
             ZipFileReader::Zstd(r) => r.into_inner().finish().into_inner().into_inner(),
+            #[cfg(feature = "xz")]
+            ZipFileReader::Xz(r) => r.into_inner().into_inner().into_inner(),
+            #[cfg(feature = "ppmd")]
+            ZipFileReader::Ppmd(r) => r.into_inner().into_inner().into_inner(),
+            ZipFileReader::Custom(r) => r.into_inner().into_inner().into_inner(),
         }
     }
 }
@@ -179,12 +263,105 @@ pub struct ZipFile<'a> {
     data: Cow<'a, ZipFileData>,
     crypto_reader: Option<CryptoReader<'a>>,
     reader: ZipFileReader<'a>,
+    codec: Option<Arc<dyn Codec>>,
+    #[cfg(feature = "zstd")]
+    zstd_dictionaries: Option<&'a ZstdDictionaries>,
+    limits: Limits,
+    bytes_read: u64,
+    local_extra_field: Vec<u8>,
+}
+
+/// A seekable reader for a file stored in a zip archive without compression or encryption
+///
+/// Created by [`ZipArchive::by_index_seekable`] and [`ZipArchive::by_name_seekable`]. Reads are
+/// bounded to the data of the file, and seeking is relative to its start.
+pub struct SeekableZipFile<'a, R> {
+    reader: &'a mut R,
+    data: &'a ZipFileData,
+    position: u64,
+}
+
+impl<'a, R> SeekableZipFile<'a, R> {
+    /// Get the name of the file
+    pub fn name(&self) -> &str {
+        &self.data.file_name
+    }
+
+    /// Get the size of the file, in bytes
+    pub fn size(&self) -> u64 {
+        self.data.uncompressed_size
+    }
+}
+
+impl<'a, R: Read + Seek> Read for SeekableZipFile<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.data.compressed_size.saturating_sub(self.position);
+        let len = (buf.len() as u64).min(remaining) as usize;
+        if len == 0 {
+            return Ok(0);
+        }
+        let data_start = self.data.data_start.load();
+        self.reader
+            .seek(io::SeekFrom::Start(data_start + self.position))?;
+        let count = self.reader.read(&mut buf[..len])?;
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<'a, R> Seek for SeekableZipFile<'a, R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let size = self.data.compressed_size;
+        seek_position(&mut self.position, pos, || Ok(size))
+    }
+}
+
+/// Move `position` as requested by `pos`, for a reader over data of size `size`.
+pub(crate) fn seek_position(
+    position: &mut u64,
+    pos: io::SeekFrom,
+    size: impl FnOnce() -> io::Result<u64>,
+) -> io::Result<u64> {
+    let (base, offset) = match pos {
+        io::SeekFrom::Start(offset) => {
+            *position = offset;
+            return Ok(offset);
+        }
+        io::SeekFrom::Current(offset) => (*position, offset),
+        io::SeekFrom::End(offset) => (size()?, offset),
+    };
+    let new_position = if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.unsigned_abs())
+    };
+    match new_position {
+        Some(new_position) => {
+            *position = new_position;
+            Ok(new_position)
+        }
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        )),
+    }
 }
 
 fn find_content<'a>(
     data: &ZipFileData,
     reader: &'a mut (impl Read + Seek),
-) -> ZipResult<io::Take<&'a mut dyn Read>> {
+) -> ZipResult<io::Take<Box<dyn Read + 'a>>> {
+    seek_to_content(data, reader, false)?;
+    Ok((Box::new(reader) as Box<dyn Read + 'a>).take(data.compressed_size))
+}
+
+/// Parse the local header of a file and seek `reader` to the start of its data, returning the
+/// extra field of the local header if `read_extra_field` is set, and an empty one otherwise.
+fn seek_to_content(
+    data: &ZipFileData,
+    reader: &mut (impl Read + Seek),
+    read_extra_field: bool,
+) -> ZipResult<Vec<u8>> {
     // Parse local header
     reader.seek(io::SeekFrom::Start(data.header_start))?;
     let signature = reader.read_u32::<LittleEndian>()?;
@@ -199,8 +376,23 @@ fn find_content<'a>(
     let data_start = data.header_start + magic_and_header + file_name_length + extra_field_length;
     data.data_start.store(data_start);
 
-    reader.seek(io::SeekFrom::Start(data_start))?;
-    Ok((reader as &mut dyn Read).take(data.compressed_size))
+    if !read_extra_field {
+        reader.seek(io::SeekFrom::Start(data_start))?;
+        return Ok(Vec::new());
+    }
+    reader.seek(io::SeekFrom::Current(file_name_length as i64))?;
+    let mut extra_field = vec![0; extra_field_length as usize];
+    reader.read_exact(&mut extra_field)?;
+    Ok(extra_field)
+}
+
+/// Check that the local header and the data of a file are within an archive of `len` bytes.
+fn check_content(data: &ZipFileData, reader: &mut (impl Read + Seek), len: u64) -> ZipResult<()> {
+    seek_to_content(data, reader, false)?;
+    if data.data_start.load().saturating_add(data.compressed_size) > len {
+        return Err(ZipError::InvalidArchive("File data is truncated"));
+    }
+    Ok(())
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -209,14 +401,15 @@ fn make_crypto_reader<'a>(
     crc32: u32,
     last_modified_time: DateTime,
     using_data_descriptor: bool,
-    reader: io::Take<&'a mut dyn io::Read>,
+    reader: io::Take<Box<dyn Read + 'a>>,
     password: Option<&[u8]>,
     aes_info: Option<(AesMode, AesVendorVersion)>,
     #[cfg(feature = "aes-crypto")] compressed_size: u64,
+    has_codec: bool,
 ) -> ZipResult<Result<CryptoReader<'a>, InvalidPassword>> {
     #[allow(deprecated)]
     {
-        if let CompressionMethod::Unsupported(_) = compression_method {
+        if let (CompressionMethod::Unsupported(_), false) = (compression_method, has_codec) {
             return unsupported_zip_error("Compression method not supported");
         }
     }
@@ -255,17 +448,40 @@ fn make_crypto_reader<'a>(
     Ok(Ok(reader))
 }
 
-fn make_reader(
+#[allow(clippy::too_many_arguments)]
+fn make_reader<'a>(
     compression_method: CompressionMethod,
     crc32: u32,
-    reader: CryptoReader,
-) -> ZipFileReader {
+    reader: CryptoReader<'a>,
+    uncompressed_size: u64,
+    #[cfg(feature = "legacy")] flags: u16,
+    #[cfg(any(feature = "lzma", feature = "xz"))] memory_limit: u64,
+    codec: Option<Arc<dyn Codec>>,
+    #[cfg(feature = "zstd")] zstd_dictionaries: Option<&'a ZstdDictionaries>,
+) -> ZipFileReader<'a> {
     let ae2_encrypted = reader.is_ae2_encrypted();
 
+    if let Some(codec) = codec {
+        let codec_reader = CodecReader::new(reader, codec, uncompressed_size);
+        return ZipFileReader::Custom(Crc32Reader::new(codec_reader, crc32, ae2_encrypted));
+    }
+
     match compression_method {
         CompressionMethod::Stored => {
             ZipFileReader::Stored(Crc32Reader::new(reader, crc32, ae2_encrypted))
         }
+        #[cfg(feature = "legacy")]
+        CompressionMethod::Shrink
+        | CompressionMethod::Reduce1
+        | CompressionMethod::Reduce2
+        | CompressionMethod::Reduce3
+        | CompressionMethod::Reduce4
+        | CompressionMethod::Implode
+        | CompressionMethod::PkwareImplode => {
+            let legacy_reader =
+                LegacyReader::new(reader, compression_method, flags, uncompressed_size);
+            ZipFileReader::Legacy(Crc32Reader::new(legacy_reader, crc32, ae2_encrypted))
+        }
         #[cfg(any(
             feature = "deflate",
             feature = "deflate-miniz",
@@ -275,16 +491,52 @@ fn make_reader(
             let deflate_reader = DeflateDecoder::new(reader);
             ZipFileReader::Deflated(Crc32Reader::new(deflate_reader, crc32, ae2_encrypted))
         }
+        #[cfg(feature = "deflate64")]
+        CompressionMethod::Deflate64 => {
+            let deflate64_reader = Deflate64Decoder::new(reader);
+            ZipFileReader::Deflate64(Crc32Reader::new(deflate64_reader, crc32, ae2_encrypted))
+        }
         #[cfg(feature = "bzip2")]
         CompressionMethod::Bzip2 => {
             let bzip2_reader = BzDecoder::new(reader);
             ZipFileReader::Bzip2(Crc32Reader::new(bzip2_reader, crc32, ae2_encrypted))
         }
+        #[cfg(feature = "lzma")]
+        CompressionMethod::Lzma => {
+            let lzma_reader = LzmaReader::new(reader, uncompressed_size, memory_limit);
+            ZipFileReader::Lzma(Crc32Reader::new(lzma_reader, crc32, ae2_encrypted))
+        }
         #[cfg(feature = "zstd")]
         CompressionMethod::Zstd => {
-            let zstd_reader = ZstdDecoder::new(reader).unwrap();
+            let mut reader = io::BufReader::new(reader);
+            // Peek at the frame header to find the dictionary it was compressed with.
+            // Errors will surface again once the decoder reads from `reader`.
+            let dictionary = match zstd_dictionaries {
+                Some(dictionaries) => reader
+                    .fill_buf()
+                    .ok()
+                    .and_then(|data| dictionaries.for_frame(data)),
+                None => None,
+            };
+            let zstd_reader = match dictionary {
+                Some(dictionary) => ZstdDecoder::with_prepared_dictionary(reader, dictionary),
+                None => ZstdDecoder::with_buffer(reader),
+            }
+            .unwrap();
             ZipFileReader::Zstd(Crc32Reader::new(zstd_reader, crc32, ae2_encrypted))
         }
+        #[cfg(feature = "xz")]
+        CompressionMethod::Xz => {
+            // Like XzDecoder::new, this only fails if liblzma can't allocate memory
+            let stream = Stream::new_stream_decoder(memory_limit, 0).unwrap();
+            let xz_reader = XzDecoder::new_stream(reader, stream);
+            ZipFileReader::Xz(Crc32Reader::new(xz_reader, crc32, ae2_encrypted))
+        }
+        #[cfg(feature = "ppmd")]
+        CompressionMethod::Ppmd => {
+            let ppmd_reader = PpmdReader::new(reader, uncompressed_size);
+            ZipFileReader::Ppmd(Crc32Reader::new(ppmd_reader, crc32, ae2_encrypted))
+        }
         _ => panic!("Compression method not supported"),
     }
 }
@@ -394,7 +646,15 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     /// Read a ZIP archive, collecting the files it contains
     ///
     /// This uses the central directory record of the ZIP file, and ignores local file headers
-    pub fn new(mut reader: R) -> ZipResult<ZipArchive<R>> {
+    pub fn new(reader: R) -> ZipResult<ZipArchive<R>> {
+        Self::with_options(reader, ReadOptions::default())
+    }
+
+    /// Read a ZIP archive like [`ZipArchive::new`] with the given options, e.g. to enforce
+    /// limits on the archive or to decode the names of its files differently. See
+    /// [`ReadOptions`].
+    pub fn with_options(mut reader: R, options: ReadOptions<'_>) -> ZipResult<ZipArchive<R>> {
+        let limits = options.limits;
         let (footer, cde_start_pos) = spec::CentralDirectoryEnd::find_and_parse(&mut reader)?;
 
         if footer.disk_number != footer.disk_with_central_directory {
@@ -404,8 +664,9 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         let (archive_offset, directory_start, number_of_files) =
             Self::get_directory_counts(&mut reader, &footer, cde_start_pos)?;
 
+        limits.check_files(number_of_files)?;
+
         let mut files = Vec::new();
-        let mut names_map = HashMap::new();
 
         if reader.seek(io::SeekFrom::Start(directory_start)).is_err() {
             return Err(ZipError::InvalidArchive(
@@ -413,53 +674,266 @@ impl<R: Read + io::Seek> ZipArchive<R> {
             ));
         }
 
+        let mut total_size = 0u64;
         for _ in 0..number_of_files {
             let file = central_header_to_zip_file(&mut reader, archive_offset)?;
-            names_map.insert(file.file_name.clone(), files.len());
+            let position = reader.stream_position()?;
+            limits.check_central_directory_size(position.saturating_sub(directory_start))?;
+            total_size = total_size.saturating_add(file.uncompressed_size);
+            limits.check_total_size(total_size)?;
             files.push(file);
         }
+        decode_names(&mut files, options.decoder);
+        let names_map = names_map(&files);
 
         let shared = Arc::new(Shared {
             files,
             names_map,
             offset: archive_offset,
+            directory_start,
             comment: footer.zip_file_comment,
         });
 
-        Ok(ZipArchive { reader, shared })
+        let mut archive = ZipArchive {
+            reader,
+            shared,
+            codecs: Codecs::default(),
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries: ZstdDictionaries::default(),
+            limits,
+            read_local_extra_fields: options.local_extra_fields,
+        };
+        if limits.rejects_overlapping_files() && !archive.find_overlaps()?.is_empty() {
+            return Err(ZipError::InvalidArchive("Overlapping files"));
+        }
+        Ok(archive)
+    }
+
+    /// Find the files in the archive that overlap each other or the central directory.
+    ///
+    /// In a valid archive, every file has its own local header and data, so this returns no
+    /// overlaps. See [`Limits::reject_overlapping_files`] for why archives may contain them. This
+    /// reads the local header of every file to find where its data starts.
+    pub fn find_overlaps(&mut self) -> ZipResult<Vec<Overlap>> {
+        for data in self.shared.files.iter() {
+            seek_to_content(data, &mut self.reader, false)?;
+        }
+        Ok(crate::limits::find_overlaps(
+            &self.shared.files,
+            self.shared.directory_start,
+        ))
+    }
+
+    /// Recover the files of an archive whose central directory is missing or damaged, by
+    /// scanning it for local file headers.
+    ///
+    /// This reads the whole archive and ignores its central directory. Everything between
+    /// the files that couldn't be read as a file is skipped, and returned as a list of byte
+    /// ranges along with the archive. Files written with a data descriptor can only be
+    /// recovered if the descriptor has the optional signature, since their sizes aren't
+    /// stored in the local header. Only the information in the local headers is available,
+    /// so e.g. the file comments and unix modes of the files are lost.
+    ///
+    /// The recovered files can be read as usual, and their CRC-32 is still checked.
+    pub fn recover(reader: R) -> ZipResult<(ZipArchive<R>, Vec<std::ops::Range<u64>>)> {
+        Self::recover_with_options(reader, ReadOptions::default())
+    }
+
+    /// Recover the files of an archive like [`ZipArchive::recover`] with the given options.
+    /// See [`ReadOptions`].
+    pub fn recover_with_options(
+        mut reader: R,
+        options: ReadOptions<'_>,
+    ) -> ZipResult<(ZipArchive<R>, Vec<std::ops::Range<u64>>)> {
+        let mut scan = crate::recover::scan(&mut reader)?;
+        options.limits.check_scanned_files(&scan.files)?;
+        decode_names(&mut scan.files, options.decoder);
+        let archive = ZipArchive::from_files(reader, scan.files, scan.end, Vec::new(), options);
+        Ok((archive, scan.skipped))
+    }
+
+    /// Create an archive from files that weren't read from its central directory.
+    fn from_files(
+        reader: R,
+        files: Vec<ZipFileData>,
+        directory_start: u64,
+        comment: Vec<u8>,
+        options: ReadOptions<'_>,
+    ) -> ZipArchive<R> {
+        let shared = Arc::new(Shared {
+            names_map: names_map(&files),
+            files,
+            offset: 0,
+            directory_start,
+            comment,
+        });
+
+        ZipArchive {
+            reader,
+            shared,
+            codecs: Codecs::default(),
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries: ZstdDictionaries::default(),
+            limits: options.limits,
+            read_local_extra_fields: options.local_extra_fields,
+        }
     }
+
+    /// Write a clean copy of a damaged archive, copying the compressed data of every file that
+    /// can be found without recompressing it.
+    ///
+    /// The files listed in the central directory are used if it can be read. The archive is
+    /// also scanned for local headers as with [`ZipArchive::recover`], which finds the files
+    /// that the central directory doesn't list, e.g. because its counts are wrong or it's
+    /// missing. Scanned files lack the information that's only stored in the central
+    /// directory. The new archive is written with [`ZipWriter`](crate::ZipWriter) and finished,
+    /// so it has consistent offsets, counts and sizes.
+    ///
+    /// Files whose local header or data can't be found, and encrypted files, which can't be
+    /// copied, are reported as failures while the other files are still copied. The data of
+    /// the files isn't checked while copying it. Use [`ZipArchive::verify`] on the repaired
+    /// archive to find files with damaged data. I/O errors while copying stop the repair.
+    pub fn repair<W: Write + io::Seek>(reader: R, writer: W) -> ZipResult<RepairReport> {
+        Self::repair_with_options(reader, writer, ReadOptions::default())
+    }
+
+    /// Repair an archive like [`ZipArchive::repair`] with the given options. See
+    /// [`ReadOptions`].
+    ///
+    /// The limits are checked for the central directory and for the files found by scanning
+    /// the archive. Exceeding them stops the repair.
+    pub fn repair_with_options<W: Write + io::Seek>(
+        mut reader: R,
+        writer: W,
+        options: ReadOptions<'_>,
+    ) -> ZipResult<RepairReport> {
+        let mut scan = crate::recover::scan(&mut reader)?;
+        options.limits.check_scanned_files(&scan.files)?;
+        decode_names(&mut scan.files, options.decoder);
+        let len = reader.seek(io::SeekFrom::End(0))?;
+
+        let mut files = Vec::new();
+        let mut failures = Vec::new();
+        let mut directory_start = scan.end;
+        let mut comment = Vec::new();
+        match ZipArchive::with_options(&mut reader, options) {
+            Ok(mut central) => {
+                for data in central.shared.files.iter() {
+                    match check_content(data, &mut central.reader, len) {
+                        Ok(()) => files.push(data.clone()),
+                        Err(error) => failures.push((data.file_name.clone(), error)),
+                    }
+                }
+                directory_start = central.shared.directory_start;
+                comment = central.shared.comment.clone();
+            }
+            Err(error @ ZipError::LimitExceeded(_)) => return Err(error),
+            Err(_) => {}
+        }
+
+        // Add the files that the central directory doesn't list, or whose header it doesn't
+        // point to
+        let listed: HashSet<u64> = files.iter().map(|data| data.header_start).collect();
+        let unlisted: Vec<_> = scan
+            .files
+            .into_iter()
+            .filter(|data| !listed.contains(&data.header_start))
+            .collect();
+        failures.retain(|(name, _)| !unlisted.iter().any(|data| &data.file_name == name));
+        let mut report = if unlisted.is_empty() {
+            RepairReport::new(None)
+        } else {
+            files.extend(unlisted);
+            files.sort_by_key(|data| data.header_start);
+            // Decode the names again, so that all of them are decoded with the same encoding
+            decode_names(&mut files, options.decoder);
+            RepairReport::new(Some(scan.skipped))
+        };
+        for (name, error) in failures {
+            report.push_failure(name, error);
+        }
+
+        let mut archive =
+            ZipArchive::from_files(&mut reader, files, directory_start, comment, options);
+        let mut output = crate::ZipWriter::new(writer);
+        output.set_raw_comment(archive.comment().to_vec());
+        for i in 0..archive.len() {
+            let data = &archive.shared.files[i];
+            let name = data.file_name.clone();
+            if data.encrypted {
+                let error = ZipError::UnsupportedArchive("Encrypted files can't be copied");
+                report.push_failure(name, error);
+                continue;
+            }
+            match archive.by_index_raw(i) {
+                Ok(file) => {
+                    output.raw_copy_file(file)?;
+                    report.push_copied(name);
+                }
+                Err(error) => report.push_failure(name, error),
+            }
+        }
+        output.finish()?;
+        Ok(report)
+    }
+
     /// Extract a Zip archive into a directory, overwriting files if they
     /// already exist. Paths are sanitized with [`ZipFile::enclosed_name`].
     ///
     /// Extraction is not atomic; If an error is encountered, some of the files
     /// may be left on disk.
     pub fn extract<P: AsRef<Path>>(&mut self, directory: P) -> ZipResult<()> {
+        self.extract_with(directory, ExtractOptions::default())
+    }
+
+    /// Extract a Zip archive into a directory with the given options. Paths are sanitized with
+    /// [`ZipFile::enclosed_name`].
+    ///
+    /// Extraction is not atomic; If an error is encountered, some of the files
+    /// may be left on disk.
+    pub fn extract_with<P: AsRef<Path>>(
+        &mut self,
+        directory: P,
+        options: ExtractOptions<'_>,
+    ) -> ZipResult<()> {
         use std::fs;
 
+        let limits = self.limits;
+        let total_size = std::sync::atomic::AtomicU64::new(0);
         for i in 0..self.len() {
             let mut file = self.by_index(i)?;
             let filepath = file
                 .enclosed_name()
                 .ok_or(ZipError::InvalidArchive("Invalid file path"))?;
+            if !options.includes(filepath) {
+                continue;
+            }
 
             let outpath = directory.as_ref().join(filepath);
 
             if file.name().ends_with('/') {
-                fs::create_dir_all(&outpath)?;
+                options.create_dir_all(&outpath)?;
             } else {
                 if let Some(p) = outpath.parent() {
                     if !p.exists() {
-                        fs::create_dir_all(&p)?;
+                        options.create_dir_all(p)?;
                     }
                 }
-                let mut outfile = fs::File::create(&outpath)?;
-                io::copy(&mut file, &mut outfile)?;
+                let modified = crate::extract::modified_time(&file);
+                let mut outfile = match options.create_file(&outpath, modified)? {
+                    Some(outfile) => outfile,
+                    None => continue,
+                };
+                limits.copy_file(&mut file, &mut outfile, &total_size)?;
+                #[cfg(feature = "filetime")]
+                options.set_modified_time(&outfile, modified)?;
             }
             // Get and Set permissions
             #[cfg(unix)]
             {
                 use std::os::unix::fs::PermissionsExt;
                 if let Some(mode) = file.unix_mode() {
+                    let mode = options.mask(mode);
                     fs::set_permissions(&outpath, fs::Permissions::from_mode(mode))?;
                 }
             }
@@ -495,6 +969,41 @@ impl<R: Read + io::Seek> ZipArchive<R> {
         self.shared.names_map.keys().map(|s| s.as_str())
     }
 
+    /// Register a codec to decompress files compressed with the method `method`.
+    ///
+    /// The codec is used instead of the built-in implementation of the method, if there is one.
+    /// Registering another codec for the same method replaces the previous one. See the
+    /// [`codec`](crate::codec) module.
+    pub fn register_codec(&mut self, method: u16, codec: impl Codec + 'static) {
+        self.codecs.insert(method, Arc::new(codec));
+    }
+
+    /// Register a Zstandard dictionary to decompress files compressed with
+    /// [`ZipWriter::set_zstd_dictionary`](crate::write::ZipWriter::set_zstd_dictionary).
+    ///
+    /// A file uses the dictionary whose ID matches the dictionary ID in its zstd frame header,
+    /// which zstd records for trained dictionaries. A raw content dictionary has no ID and is used
+    /// for files that don't reference a dictionary by ID. Registering another dictionary with the
+    /// same ID replaces the previous one.
+    #[cfg(feature = "zstd")]
+    pub fn add_zstd_dictionary(&mut self, dictionary: &[u8]) {
+        let id = zstd::zstd_safe::get_dict_id_from_dict(dictionary);
+        self.zstd_dictionaries
+            .0
+            .insert(id, Arc::new(DecoderDictionary::copy(dictionary)));
+    }
+
+    /// Register a Zstandard dictionary that is stored as a file in this archive.
+    ///
+    /// See [`ZipArchive::add_zstd_dictionary`].
+    #[cfg(feature = "zstd")]
+    pub fn add_zstd_dictionary_by_name(&mut self, name: &str) -> ZipResult<()> {
+        let mut dictionary = Vec::new();
+        self.by_name(name)?.copy_to(&mut dictionary, u64::MAX)?;
+        self.add_zstd_dictionary(&dictionary);
+        Ok(())
+    }
+
     /// Search for a file entry by name, decrypt with given password
     pub fn by_name_decrypt<'a>(
         &'a mut self,
@@ -542,56 +1051,182 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     /// Get a contained file by index without decompressing it
     pub fn by_index_raw(&mut self, file_number: usize) -> ZipResult<ZipFile<'_>> {
         let reader = &mut self.reader;
+        let read_local_extra_fields = self.read_local_extra_fields;
         self.shared
             .files
             .get(file_number)
             .ok_or(ZipError::FileNotFound)
             .and_then(move |data| {
+                let local_extra_field = seek_to_content(data, reader, read_local_extra_fields)?;
+                let content = (Box::new(reader) as Box<dyn Read + '_>).take(data.compressed_size);
                 Ok(ZipFile {
                     crypto_reader: None,
-                    reader: ZipFileReader::Raw(find_content(data, reader)?),
+                    reader: ZipFileReader::Raw(content),
                     data: Cow::Borrowed(data),
+                    codec: None,
+                    #[cfg(feature = "zstd")]
+                    zstd_dictionaries: None,
+                    limits: Limits::default(),
+                    bytes_read: 0,
+                    local_extra_field,
                 })
             })
     }
 
-    fn by_index_with_optional_password<'a>(
+    /// Get a contained file by index as a seekable reader.
+    ///
+    /// Only files that are stored without compression or encryption can be read this way. The
+    /// CRC-32 of the file isn't checked, as the file may be read out of order or only in part.
+    pub fn by_index_seekable(&mut self, file_number: usize) -> ZipResult<SeekableZipFile<'_, R>> {
+        let data = self
+            .shared
+            .files
+            .get(file_number)
+            .ok_or(ZipError::FileNotFound)?;
+        if data.encrypted || data.compression_method != CompressionMethod::Stored {
+            return unsupported_zip_error(
+                "Only stored, unencrypted files can be read with seeking",
+            );
+        }
+        find_content(data, &mut self.reader)?;
+        Ok(SeekableZipFile {
+            reader: &mut self.reader,
+            data,
+            position: 0,
+        })
+    }
+
+    /// Search for a file entry by name and get it as a seekable reader.
+    ///
+    /// See [`ZipArchive::by_index_seekable`].
+    pub fn by_name_seekable(&mut self, name: &str) -> ZipResult<SeekableZipFile<'_, R>> {
+        let index = match self.shared.names_map.get(name) {
+            Some(index) => *index,
+            None => {
+                return Err(ZipError::FileNotFound);
+            }
+        };
+        self.by_index_seekable(index)
+    }
+
+    /// Decompress a contained file to build an index of checkpoints for random access.
+    ///
+    /// A checkpoint is recorded whenever at least `spacing` bytes were decompressed since the
+    /// last one, at the first place where decoding can be resumed. Smaller spacing makes seeking
+    /// faster but the index larger. Only unencrypted deflate and zstd files are supported. See
+    /// [`CheckpointIndex`].
+    pub fn build_checkpoint_index(
+        &mut self,
+        file_number: usize,
+        spacing: u64,
+    ) -> ZipResult<CheckpointIndex> {
+        let data = self
+            .shared
+            .files
+            .get(file_number)
+            .ok_or(ZipError::FileNotFound)?;
+        crate::checkpoint::check_supported(data)?;
+        find_content(data, &mut self.reader)?;
+        CheckpointIndex::build(
+            data,
+            &mut self.reader,
+            spacing,
+            #[cfg(feature = "zstd")]
+            Some(&self.zstd_dictionaries),
+        )
+    }
+
+    /// Get a contained file by index as a seekable reader, which decompresses from the nearest
+    /// checkpoint in `index`.
+    ///
+    /// The index must have been built by [`ZipArchive::build_checkpoint_index`] for this file.
+    pub fn by_index_with_checkpoints<'a>(
         &'a mut self,
         file_number: usize,
-        mut password: Option<&[u8]>,
-    ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
+        index: &'a CheckpointIndex,
+    ) -> ZipResult<CheckpointedZipFile<'a, R>> {
         let data = self
             .shared
             .files
             .get(file_number)
             .ok_or(ZipError::FileNotFound)?;
+        crate::checkpoint::check_supported(data)?;
+        index.check(data)?;
+        find_content(data, &mut self.reader)?;
+        Ok(CheckpointedZipFile::new(
+            &mut self.reader,
+            data,
+            index,
+            #[cfg(feature = "zstd")]
+            Some(&self.zstd_dictionaries),
+        ))
+    }
 
-        match (password, data.encrypted) {
-            (None, true) => return Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)),
-            (Some(_), false) => password = None, //Password supplied, but none needed! Discard.
-            _ => {}
+    /// Test the integrity of every file in the archive, like `unzip -t`.
+    ///
+    /// Every file is decompressed to check its CRC-32, or its authentication code if it's
+    /// encrypted with AES, and the size of its data. Its local header and data descriptor are
+    /// compared with its central directory entry. Unlike reading the files one by one, this
+    /// doesn't stop at the first problem, but reports all problems for every file.
+    ///
+    /// Encrypted files can't be read without a password, which is reported as an error. Use
+    /// [`ZipArchive::verify_decrypt`] to verify them.
+    pub fn verify(&mut self) -> VerifyReport {
+        self.verify_with_optional_password(None)
+    }
+
+    /// Test the integrity of every file in the archive, decrypting encrypted files with the
+    /// given password.
+    ///
+    /// See [`ZipArchive::verify`].
+    pub fn verify_decrypt(&mut self, password: &[u8]) -> VerifyReport {
+        self.verify_with_optional_password(Some(password))
+    }
+
+    fn verify_with_optional_password(&mut self, password: Option<&[u8]>) -> VerifyReport {
+        let shared = self.shared.clone();
+        let mut files = Vec::new();
+        for (i, data) in shared.files.iter().enumerate() {
+            let mut problems = Vec::new();
+            if let Err(e) = crate::verify::check_headers(data, &mut self.reader, &mut problems) {
+                problems.push(VerifyProblem::Error(e));
+            }
+            match self.by_index_with_optional_password(i, password) {
+                Ok(Ok(mut file)) => match file.copy_to(&mut io::sink(), u64::MAX) {
+                    Ok(size) if size != data.uncompressed_size => {
+                        problems.push(VerifyProblem::SizeMismatch)
+                    }
+                    Ok(_) => {}
+                    Err(e) => problems.push(VerifyProblem::Error(e)),
+                },
+                Ok(Err(InvalidPassword)) => problems.push(VerifyProblem::InvalidPassword),
+                Err(e) => problems.push(VerifyProblem::Error(e)),
+            }
+            files.push(FileReport::new(i, data.file_name.clone(), problems));
         }
-        let limit_reader = find_content(data, &mut self.reader)?;
-
-        match make_crypto_reader(
-            data.compression_method,
-            data.crc32,
-            data.last_modified_time,
-            data.using_data_descriptor,
-            limit_reader,
+        VerifyReport::new(files)
+    }
+
+    fn by_index_with_optional_password<'a>(
+        &'a mut self,
+        file_number: usize,
+        password: Option<&[u8]>,
+    ) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
+        let data = self
+            .shared
+            .files
+            .get(file_number)
+            .ok_or(ZipError::FileNotFound)?;
+        open_zip_file(
+            data,
+            &mut self.reader,
             password,
-            data.aes_mode,
-            #[cfg(feature = "aes-crypto")]
-            data.compressed_size,
-        ) {
-            Ok(Ok(crypto_reader)) => Ok(Ok(ZipFile {
-                crypto_reader: Some(crypto_reader),
-                reader: ZipFileReader::NoReader,
-                data: Cow::Borrowed(data),
-            })),
-            Err(e) => Err(e),
-            Ok(Err(e)) => Ok(Err(e)),
-        }
+            &self.codecs,
+            #[cfg(feature = "zstd")]
+            &self.zstd_dictionaries,
+            self.limits,
+            self.read_local_extra_fields,
+        )
     }
 
     /// Unwrap and return the inner reader object
@@ -602,11 +1237,337 @@ impl<R: Read + io::Seek> ZipArchive<R> {
     }
 }
 
+/// Zero-copy access for archives held in memory, such as a byte slice or a memory map.
+impl<T: AsRef<[u8]>> ZipArchive<io::Cursor<T>> {
+    /// Borrow the contents of a contained file by index, without copying.
+    ///
+    /// Only stored, unencrypted files can be borrowed, since anything else has to be
+    /// decompressed or decrypted first. The CRC-32 of the contents is not checked.
+    pub fn by_index_slice(&self, file_number: usize) -> ZipResult<&[u8]> {
+        let data = self
+            .shared
+            .files
+            .get(file_number)
+            .ok_or(ZipError::FileNotFound)?;
+        if data.encrypted || data.compression_method != CompressionMethod::Stored {
+            return unsupported_zip_error("Only stored, unencrypted files can be borrowed");
+        }
+        self.content_slice(data)
+    }
+
+    /// Borrow the contents of a contained file by name, without copying.
+    ///
+    /// See [`ZipArchive::by_index_slice`].
+    pub fn by_name_slice(&self, name: &str) -> ZipResult<&[u8]> {
+        match self.shared.names_map.get(name) {
+            Some(index) => self.by_index_slice(*index),
+            None => Err(ZipError::FileNotFound),
+        }
+    }
+
+    /// Borrow the raw data of a contained file by index, without copying.
+    ///
+    /// The data is returned as it is stored in the archive, so it is still compressed and
+    /// encrypted according to the file's compression method and encryption.
+    pub fn by_index_raw_slice(&self, file_number: usize) -> ZipResult<&[u8]> {
+        let data = self
+            .shared
+            .files
+            .get(file_number)
+            .ok_or(ZipError::FileNotFound)?;
+        self.content_slice(data)
+    }
+
+    /// Borrow the raw data of a contained file by name, without copying.
+    ///
+    /// See [`ZipArchive::by_index_raw_slice`].
+    pub fn by_name_raw_slice(&self, name: &str) -> ZipResult<&[u8]> {
+        match self.shared.names_map.get(name) {
+            Some(index) => self.by_index_raw_slice(*index),
+            None => Err(ZipError::FileNotFound),
+        }
+    }
+
+    /// Like [`find_content`], but parses the local header straight from memory.
+    fn content_slice(&self, data: &ZipFileData) -> ZipResult<&[u8]> {
+        let bytes = self.reader.get_ref().as_ref();
+        let header = usize::try_from(data.header_start)
+            .ok()
+            .and_then(|start| bytes.get(start..))
+            .filter(|header| header.len() >= 30)
+            .ok_or(ZipError::InvalidArchive("Invalid local file header"))?;
+        if (&header[..4]).read_u32::<LittleEndian>()? != spec::LOCAL_FILE_HEADER_SIGNATURE {
+            return Err(ZipError::InvalidArchive("Invalid local file header"));
+        }
+        let file_name_length = (&header[26..]).read_u16::<LittleEndian>()? as u64;
+        let extra_field_length = (&header[28..]).read_u16::<LittleEndian>()? as u64;
+        let data_start = data.header_start + 30 + file_name_length + extra_field_length;
+        data.data_start.store(data_start);
+
+        data_start
+            .checked_add(data.compressed_size)
+            .and_then(|end| Some(usize::try_from(data_start).ok()?..usize::try_from(end).ok()?))
+            .and_then(|range| bytes.get(range))
+            .ok_or(ZipError::InvalidArchive(
+                "File data extends past the end of the archive",
+            ))
+    }
+}
+
+/// Concurrent reads through a shared reference, for archives opened from a [`ReadAtCursor`].
+impl<T: ReadAt> ZipArchive<ReadAtCursor<T>> {
+    /// Get a contained file by index through a shared reference.
+    ///
+    /// Every file gets a cursor of its own into the underlying [`ReadAt`] source, so several
+    /// threads can read files from the archive at once.
+    pub fn by_index_shared(&self, file_number: usize) -> ZipResult<ZipFile<'_>> {
+        Ok(self
+            .by_index_shared_with_optional_password(file_number, None)?
+            .unwrap())
+    }
+
+    /// Get a contained file by index through a shared reference, decrypting it with the given
+    /// password.
+    ///
+    /// See [`ZipArchive::by_index_shared`] and [`ZipArchive::by_index_decrypt`].
+    pub fn by_index_shared_decrypt(
+        &self,
+        file_number: usize,
+        password: &[u8],
+    ) -> ZipResult<Result<ZipFile<'_>, InvalidPassword>> {
+        self.by_index_shared_with_optional_password(file_number, Some(password))
+    }
+
+    /// Search for a file entry by name through a shared reference.
+    ///
+    /// See [`ZipArchive::by_index_shared`].
+    pub fn by_name_shared(&self, name: &str) -> ZipResult<ZipFile<'_>> {
+        match self.shared.names_map.get(name) {
+            Some(index) => self.by_index_shared(*index),
+            None => Err(ZipError::FileNotFound),
+        }
+    }
+
+    fn by_index_shared_with_optional_password(
+        &self,
+        file_number: usize,
+        password: Option<&[u8]>,
+    ) -> ZipResult<Result<ZipFile<'_>, InvalidPassword>> {
+        let data = self
+            .shared
+            .files
+            .get(file_number)
+            .ok_or(ZipError::FileNotFound)?;
+        open_zip_file(
+            data,
+            ReadAtCursor::new(self.reader.get_ref()),
+            password,
+            &self.codecs,
+            #[cfg(feature = "zstd")]
+            &self.zstd_dictionaries,
+            self.limits,
+            self.read_local_extra_fields,
+        )
+    }
+}
+
+impl<T: ReadAt + Send + Sync + 'static> ZipArchive<ReadAtCursor<T>> {
+    /// Extract a Zip archive into a directory like [`ZipArchive::extract_with`], writing up to
+    /// `workers` files at once from separate threads.
+    ///
+    /// All directories are created before any files are written. The workers share this archive
+    /// and read its files with [`ZipArchive::by_index_shared`].
+    ///
+    /// Extraction is not atomic; If an error is encountered, some of the files
+    /// may be left on disk.
+    pub fn extract_parallel<P: AsRef<Path>>(
+        self: &Arc<Self>,
+        directory: P,
+        options: ExtractOptions<'_>,
+        workers: usize,
+    ) -> ZipResult<()> {
+        use std::fs;
+        use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+        let directory = directory.as_ref();
+        let mut files = Vec::new();
+        #[cfg(unix)]
+        let mut directories = Vec::new();
+        for (i, data) in self.shared.files.iter().enumerate() {
+            let filepath = data
+                .enclosed_name()
+                .ok_or(ZipError::InvalidArchive("Invalid file path"))?;
+            if !options.includes(filepath) {
+                continue;
+            }
+            let outpath = directory.join(filepath);
+
+            if data.file_name.ends_with('/') {
+                options.create_dir_all(&outpath)?;
+                #[cfg(unix)]
+                directories.push((outpath, data.unix_mode()));
+            } else {
+                if let Some(p) = outpath.parent() {
+                    if !p.exists() {
+                        options.create_dir_all(p)?;
+                    }
+                }
+                files.push((i, outpath));
+            }
+        }
+
+        // Workers take the next file from a shared counter until all files are written, or
+        // until one of them fails
+        let files = Arc::new(files);
+        let next = Arc::new(AtomicUsize::new(0));
+        let failed = Arc::new(AtomicBool::new(false));
+        let total_size = Arc::new(AtomicU64::new(0));
+        // The filter has already been applied, and may not live as long as the workers
+        let worker_options = options.without_filter();
+        let threads: Vec<_> = (0..workers.max(1).min(files.len()))
+            .map(|_| {
+                let archive = self.clone();
+                let files = files.clone();
+                let next = next.clone();
+                let failed = failed.clone();
+                let total_size = total_size.clone();
+                std::thread::spawn(move || {
+                    while !failed.load(Ordering::Relaxed) {
+                        let (file_number, outpath) =
+                            match files.get(next.fetch_add(1, Ordering::Relaxed)) {
+                                Some(file) => file,
+                                None => break,
+                            };
+                        let result = archive.extract_file(
+                            *file_number,
+                            outpath,
+                            &worker_options,
+                            &total_size,
+                        );
+                        if let Err(e) = result {
+                            failed.store(true, Ordering::Relaxed);
+                            return Err(e);
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+
+        let mut result = Ok(());
+        for thread in threads {
+            match thread.join() {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+                Err(panic) => {
+                    failed.store(true, Ordering::Relaxed);
+                    std::panic::resume_unwind(panic);
+                }
+            }
+        }
+        result?;
+
+        // Directory permissions are set last, since they may not allow writing the files
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for (outpath, mode) in directories {
+                if let Some(mode) = mode {
+                    fs::set_permissions(&outpath, fs::Permissions::from_mode(options.mask(mode)))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Extract a single file, whose parent directory must already exist.
+    fn extract_file(
+        &self,
+        file_number: usize,
+        outpath: &Path,
+        options: &ExtractOptions<'_>,
+        total_size: &std::sync::atomic::AtomicU64,
+    ) -> ZipResult<()> {
+        let mut file = self.by_index_shared(file_number)?;
+        let modified = crate::extract::modified_time(&file);
+        let mut outfile = match options.create_file(outpath, modified)? {
+            Some(outfile) => outfile,
+            None => return Ok(()),
+        };
+        self.limits.copy_file(&mut file, &mut outfile, total_size)?;
+        #[cfg(feature = "filetime")]
+        options.set_modified_time(&outfile, modified)?;
+        #[cfg(unix)]
+        {
+            use std::fs;
+            use std::os::unix::fs::PermissionsExt;
+            if let Some(mode) = file.unix_mode() {
+                fs::set_permissions(outpath, fs::Permissions::from_mode(options.mask(mode)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn open_zip_file<'a>(
+    data: &'a ZipFileData,
+    mut reader: impl Read + Seek + 'a,
+    mut password: Option<&[u8]>,
+    codecs: &Codecs,
+    #[cfg(feature = "zstd")] zstd_dictionaries: &'a ZstdDictionaries,
+    limits: Limits,
+    read_local_extra_field: bool,
+) -> ZipResult<Result<ZipFile<'a>, InvalidPassword>> {
+    match (password, data.encrypted) {
+        (None, true) => return Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)),
+        (Some(_), false) => password = None, //Password supplied, but none needed! Discard.
+        _ => {}
+    }
+    limits.check_file_size(data, data.uncompressed_size)?;
+    let local_extra_field = seek_to_content(data, &mut reader, read_local_extra_field)?;
+    let limit_reader = (Box::new(reader) as Box<dyn Read + 'a>).take(data.compressed_size);
+    let codec = codecs.get(data.compression_method).cloned();
+
+    match make_crypto_reader(
+        data.compression_method,
+        data.crc32,
+        data.last_modified_time,
+        data.using_data_descriptor,
+        limit_reader,
+        password,
+        data.aes_mode,
+        #[cfg(feature = "aes-crypto")]
+        data.compressed_size,
+        codec.is_some(),
+    ) {
+        Ok(Ok(crypto_reader)) => Ok(Ok(ZipFile {
+            crypto_reader: Some(crypto_reader),
+            reader: ZipFileReader::NoReader,
+            data: Cow::Borrowed(data),
+            codec,
+            #[cfg(feature = "zstd")]
+            zstd_dictionaries: Some(zstd_dictionaries),
+            limits,
+            bytes_read: 0,
+            local_extra_field,
+        })),
+        Err(e) => Err(e),
+        Ok(Err(e)) => Ok(Err(e)),
+    }
+}
+
 fn unsupported_zip_error<T>(detail: &'static str) -> ZipResult<T> {
     Err(ZipError::UnsupportedArchive(detail))
 }
 
 /// Parse a central directory entry to collect the information for the file.
+///
+/// The name and comment are only decoded if they're flagged as UTF-8, the others are decoded by
+/// [`decode_names`] once all files have been read.
 pub(crate) fn central_header_to_zip_file<R: Read + io::Seek>(
     reader: &mut R,
     archive_offset: u64,
@@ -646,11 +1607,11 @@ pub(crate) fn central_header_to_zip_file<R: Read + io::Seek>(
 
     let file_name = match is_utf8 {
         true => String::from_utf8_lossy(&*file_name_raw).into_owned(),
-        false => file_name_raw.clone().from_cp437(),
+        false => String::new(),
     };
     let file_comment = match is_utf8 {
         true => String::from_utf8_lossy(&*file_comment_raw).into_owned(),
-        false => file_comment_raw.from_cp437(),
+        false => String::new(),
     };
 
     // Construct the result
@@ -659,6 +1620,7 @@ pub(crate) fn central_header_to_zip_file<R: Read + io::Seek>(
         version_made_by: version_made_by as u8,
         encrypted,
         using_data_descriptor,
+        flags,
         compression_method: {
             #[allow(deprecated)]
             CompressionMethod::from_u16(compression_method)
@@ -671,6 +1633,7 @@ pub(crate) fn central_header_to_zip_file<R: Read + io::Seek>(
         file_name_raw,
         extra_field,
         file_comment,
+        file_comment_raw,
         header_start: offset,
         central_header_start,
         data_start: AtomicU64::new(0),
@@ -700,6 +1663,61 @@ pub(crate) fn central_header_to_zip_file<R: Read + io::Seek>(
     Ok(result)
 }
 
+/// Map the names of the files to their indices, keeping the last of files with the same name.
+fn names_map(files: &[ZipFileData]) -> HashMap<String, usize> {
+    let mut names_map = HashMap::new();
+    for (index, file) in files.iter().enumerate() {
+        names_map.insert(file.file_name.clone(), index);
+    }
+    names_map
+}
+
+/// Decode the names and comments of the files that aren't flagged as UTF-8, all at once so that
+/// the decoder can choose an encoding for all of them.
+pub(crate) fn decode_names(files: &mut [ZipFileData], decoder: &dyn NameDecoder) {
+    let is_utf8 = |file: &ZipFileData| file.flags & (1 << 11) != 0;
+    let mut raw: Vec<&[u8]> = Vec::new();
+    for file in files.iter().filter(|file| !is_utf8(file)) {
+        raw.push(&file.file_name_raw);
+        raw.push(&file.file_comment_raw);
+    }
+    let mut decoded = decoder.decode_all(&raw).into_iter();
+    for file in files.iter_mut().filter(|file| !is_utf8(file)) {
+        file.file_name = decoded.next().unwrap_or_default();
+        file.file_comment = decoded.next().unwrap_or_default();
+        apply_unicode_extra_fields(file);
+    }
+}
+
+/// Replace the name and comment of a file with the ones in its Info-ZIP Unicode Path and
+/// Unicode Comment extra fields. The fields are ignored if their CRC-32 doesn't match the raw
+/// name or comment in the header, since the header was changed by a tool that didn't know them.
+fn apply_unicode_extra_fields(file: &mut ZipFileData) {
+    let mut file_name = None;
+    let mut file_comment = None;
+    for field in ExtraFields::new(&file.extra_field).flatten() {
+        match field {
+            ExtraField::UnicodePath { crc32, name }
+                if crc32 == crc32fast::hash(&file.file_name_raw) =>
+            {
+                file_name = Some(name.to_owned());
+            }
+            ExtraField::UnicodeComment { crc32, comment }
+                if crc32 == crc32fast::hash(&file.file_comment_raw) =>
+            {
+                file_comment = Some(comment.to_owned());
+            }
+            _ => {}
+        }
+    }
+    if let Some(file_name) = file_name {
+        file.file_name = file_name;
+    }
+    if let Some(file_comment) = file_comment {
+        file.file_comment = file_comment;
+    }
+}
+
 fn parse_extra_field(file: &mut ZipFileData) -> ZipResult<()> {
     let mut reader = io::Cursor::new(&file.extra_field);
 
@@ -775,11 +1793,28 @@ impl<'a> ZipFile<'a> {
         if let ZipFileReader::NoReader = self.reader {
             let data = &self.data;
             let crypto_reader = self.crypto_reader.take().expect("Invalid reader state");
-            self.reader = make_reader(data.compression_method, data.crc32, crypto_reader)
+            self.reader = make_reader(
+                data.compression_method,
+                data.crc32,
+                crypto_reader,
+                data.uncompressed_size,
+                #[cfg(feature = "legacy")]
+                data.flags,
+                #[cfg(any(feature = "lzma", feature = "xz"))]
+                self.limits.decompression_memory_limit(),
+                self.codec.take(),
+                #[cfg(feature = "zstd")]
+                self.zstd_dictionaries,
+            )
         }
         &mut self.reader
     }
 
+    /// Get the general purpose flag of the file
+    pub(crate) fn flags(&self) -> u16 {
+        self.data.flags
+    }
+
     pub(crate) fn get_raw_reader(&mut self) -> &mut dyn Read {
         if let ZipFileReader::NoReader = self.reader {
             let crypto_reader = self.crypto_reader.take().expect("Invalid reader state");
@@ -798,6 +1833,9 @@ impl<'a> ZipFile<'a> {
 
     /// Get the name of the file
     ///
+    /// Names that aren't flagged as UTF-8 are decoded as CP437, unless the file has an
+    /// Info-ZIP Unicode Path extra field that matches the raw name.
+    ///
     /// # Warnings
     ///
     /// It is dangerous to use this name directly when extracting an archive.
@@ -841,7 +1879,7 @@ impl<'a> ZipFile<'a> {
     /// `foo/../bar` as `foo/bar` (instead of `bar`). Because of this,
     /// [`ZipFile::enclosed_name`] is the better option in most scenarios.
     ///
-    /// [`ParentDir`]: `Component::ParentDir`
+    /// [`ParentDir`]: std::path::Component::ParentDir
     pub fn mangled_name(&self) -> ::std::path::PathBuf {
         self.data.file_name_sanitized()
     }
@@ -857,23 +1895,13 @@ impl<'a> ZipFile<'a> {
     /// to path-based exploits. It is recommended over
     /// [`ZipFile::mangled_name`].
     pub fn enclosed_name(&self) -> Option<&Path> {
-        if self.data.file_name.contains('\0') {
-            return None;
-        }
-        let path = Path::new(&self.data.file_name);
-        let mut depth = 0usize;
-        for component in path.components() {
-            match component {
-                Component::Prefix(_) | Component::RootDir => return None,
-                Component::ParentDir => depth = depth.checked_sub(1)?,
-                Component::Normal(_) => depth += 1,
-                Component::CurDir => (),
-            }
-        }
-        Some(path)
+        self.data.enclosed_name()
     }
 
     /// Get the comment of the file
+    ///
+    /// Like the name, a comment that isn't flagged as UTF-8 is replaced by the one in an
+    /// Info-ZIP Unicode Comment extra field that matches it.
     pub fn comment(&self) -> &str {
         &self.data.file_comment
     }
@@ -913,27 +1941,7 @@ impl<'a> ZipFile<'a> {
 
     /// Get unix mode for the file
     pub fn unix_mode(&self) -> Option<u32> {
-        if self.data.external_attributes == 0 {
-            return None;
-        }
-
-        match self.data.system {
-            System::Unix => Some(self.data.external_attributes >> 16),
-            System::Dos => {
-                // Interpret MSDOS directory bit
-                let mut mode = if 0x10 == (self.data.external_attributes & 0x10) {
-                    ffi::S_IFDIR | 0o0775
-                } else {
-                    ffi::S_IFREG | 0o0664
-                };
-                if 0x01 == (self.data.external_attributes & 0x01) {
-                    // Read-only bit; strip write permissions
-                    mode &= 0o0555;
-                }
-                Some(mode)
-            }
-            _ => None,
-        }
+        self.data.unix_mode()
     }
 
     /// Get the CRC32 hash of the original file
@@ -946,6 +1954,28 @@ impl<'a> ZipFile<'a> {
         &self.data.extra_field
     }
 
+    /// Get the records of the extra data of the zip header for this file. See
+    /// [`ZipFile::extra_data`].
+    pub fn extra_fields(&self) -> ExtraFields<'_> {
+        ExtraFields::new(&self.data.extra_field)
+    }
+
+    /// Get the extra data of the local header for this file, which may differ from the extra
+    /// data of the central directory
+    ///
+    /// The local header is only read for its extra data if the archive was opened with
+    /// [`ReadOptions::local_extra_fields`], so it's empty otherwise. Files read with
+    /// [`read_zipfile_from_stream`] always have it.
+    pub fn local_extra_data(&self) -> &[u8] {
+        &self.local_extra_field
+    }
+
+    /// Get the records of the extra data of the local header for this file. See
+    /// [`ZipFile::local_extra_data`].
+    pub fn local_extra_fields(&self) -> ExtraFields<'_> {
+        ExtraFields::new(&self.local_extra_field)
+    }
+
     /// Get the starting offset of the data of the compressed file
     pub fn data_start(&self) -> u64 {
         self.data.data_start.load()
@@ -961,9 +1991,53 @@ impl<'a> ZipFile<'a> {
     }
 }
 
+impl<'a> ZipFile<'a> {
+    /// Read decompressed data like [`Read::read`], but fail with [`ZipError::LimitExceeded`]
+    /// rather than an I/O error if the data exceeds the limits.
+    fn read_within_limits(&mut self, buf: &mut [u8]) -> ZipResult<usize> {
+        #[cfg(feature = "ppmd")]
+        if let ZipFileReader::Ppmd(reader) = self.get_reader() {
+            let memory_size = reader.get_mut().memory_size()?;
+            self.limits.check_decompression_memory(memory_size as u64)?;
+        }
+        let result = self.get_reader().read(buf);
+        #[cfg(any(feature = "lzma", feature = "xz"))]
+        let result = result.map_err(crate::limits::from_lzma_error);
+        let count = result?;
+        self.bytes_read += count as u64;
+        self.limits.check_file_size(&self.data, self.bytes_read)?;
+        Ok(count)
+    }
+
+    /// Copy at most `max` bytes of decompressed data to `output`, returning the number of bytes
+    /// copied.
+    pub(crate) fn copy_to(&mut self, output: &mut impl Write, max: u64) -> ZipResult<u64> {
+        let mut buffer = [0; 8 * 1024];
+        let mut copied = 0;
+        while copied < max {
+            let len = (max - copied).min(buffer.len() as u64) as usize;
+            let count = match self.read_within_limits(&mut buffer[..len]) {
+                Ok(0) => break,
+                Ok(count) => count,
+                Err(ZipError::Io(e)) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            output.write_all(&buffer[..count])?;
+            copied += count as u64;
+        }
+        Ok(copied)
+    }
+}
+
 impl<'a> Read for ZipFile<'a> {
+    /// Exceeding the limits of the archive fails with an error of kind
+    /// [`io::ErrorKind::Other`] that wraps [`ZipError::LimitExceeded`].
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.get_reader().read(buf)
+        match self.read_within_limits(buf) {
+            Ok(count) => Ok(count),
+            Err(ZipError::Io(err)) => Err(err),
+            Err(err) => Err(err.into()),
+        }
     }
 }
 
@@ -975,7 +2049,7 @@ impl<'a> Drop for ZipFile<'a> {
             let mut buffer = [0; 1 << 16];
 
             // Get the inner `Take` reader so all decryption, decompression and CRC calculation is skipped.
-            let mut reader: std::io::Take<&mut dyn std::io::Read> = match &mut self.reader {
+            let mut reader: std::io::Take<Box<dyn Read>> = match &mut self.reader {
                 ZipFileReader::NoReader => {
                     let innerreader = ::std::mem::replace(&mut self.crypto_reader, None);
                     innerreader.expect("Invalid reader state").into_inner()
@@ -1000,33 +2074,10 @@ impl<'a> Drop for ZipFile<'a> {
     }
 }
 
-/// Read ZipFile structures from a non-seekable reader.
+/// Parse a local file header, after its signature.
 ///
-/// This is an alternative method to read a zip file. If possible, use the ZipArchive functions
-/// as some information will be missing when reading this manner.
-///
-/// Reads a file header from the start of the stream. Will return `Ok(Some(..))` if a file is
-/// present at the start of the stream. Returns `Ok(None)` if the start of the central directory
-/// is encountered. No more files should be read after this.
-///
-/// The Drop implementation of ZipFile ensures that the reader will be correctly positioned after
-/// the structure is done.
-///
-/// Missing fields are:
-/// * `comment`: set to an empty string
-/// * `data_start`: set to 0
-/// * `external_attributes`: `unix_mode()`: will return None
-pub fn read_zipfile_from_stream<'a, R: io::Read>(
-    reader: &'a mut R,
-) -> ZipResult<Option<ZipFile<'_>>> {
-    let signature = reader.read_u32::<LittleEndian>()?;
-
-    match signature {
-        spec::LOCAL_FILE_HEADER_SIGNATURE => (),
-        spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE => return Ok(None),
-        _ => return Err(ZipError::InvalidArchive("Invalid local file header")),
-    }
-
+/// The name is only decoded if it's flagged as UTF-8, see [`decode_names`].
+pub(crate) fn parse_local_header(reader: &mut impl Read) -> ZipResult<ZipFileData> {
     let version_made_by = reader.read_u16::<LittleEndian>()?;
     let flags = reader.read_u16::<LittleEndian>()?;
     let encrypted = flags & 1 == 1;
@@ -1049,7 +2100,7 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
 
     let file_name = match is_utf8 {
         true => String::from_utf8_lossy(&*file_name_raw).into_owned(),
-        false => file_name_raw.clone().from_cp437(),
+        false => String::new(),
     };
 
     let mut result = ZipFileData {
@@ -1057,6 +2108,7 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         version_made_by: version_made_by as u8,
         encrypted,
         using_data_descriptor,
+        flags,
         compression_method,
         last_modified_time: DateTime::from_msdos(last_mod_date, last_mod_time),
         crc32,
@@ -1066,6 +2118,7 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         file_name_raw,
         extra_field,
         file_comment: String::new(), // file comment is only available in the central directory
+        file_comment_raw: Vec::new(),
         // header_start and data start are not available, but also don't matter, since seeking is
         // not available.
         header_start: 0,
@@ -1083,18 +2136,89 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         Ok(..) | Err(ZipError::Io(..)) => {}
         Err(e) => return Err(e),
     }
+    Ok(result)
+}
 
-    if encrypted {
+/// Decompress the data of an unencrypted file, to check its CRC-32.
+pub(crate) fn check_file_data(data: &ZipFileData, reader: impl Read + Seek) -> ZipResult<()> {
+    #[cfg(feature = "zstd")]
+    let zstd_dictionaries = ZstdDictionaries::default();
+    let file = open_zip_file(
+        data,
+        reader,
+        None,
+        &Codecs::default(),
+        #[cfg(feature = "zstd")]
+        &zstd_dictionaries,
+        Limits::default(),
+        false,
+    )?;
+    match file {
+        Ok(mut file) => {
+            file.copy_to(&mut io::sink(), u64::MAX)?;
+            Ok(())
+        }
+        Err(InvalidPassword) => Err(ZipError::UnsupportedArchive(ZipError::PASSWORD_REQUIRED)),
+    }
+}
+
+/// Read ZipFile structures from a non-seekable reader.
+///
+/// This is an alternative method to read a zip file. If possible, use the ZipArchive functions
+/// as some information will be missing when reading this manner.
+///
+/// Reads a file header from the start of the stream. Will return `Ok(Some(..))` if a file is
+/// present at the start of the stream. Returns `Ok(None)` if the start of the central directory
+/// is encountered. No more files should be read after this.
+///
+/// The Drop implementation of ZipFile ensures that the reader will be correctly positioned after
+/// the structure is done. Custom [codecs](crate::codec) can't be used with this function.
+///
+/// Missing fields are:
+/// * `comment`: set to an empty string
+/// * `data_start`: set to 0
+/// * `external_attributes`: `unix_mode()`: will return None
+pub fn read_zipfile_from_stream<R: io::Read>(reader: &mut R) -> ZipResult<Option<ZipFile<'_>>> {
+    read_zipfile_from_stream_with_options(reader, ReadOptions::default())
+}
+
+/// Read ZipFile structures from a non-seekable reader like [`read_zipfile_from_stream`] with
+/// the given options. See [`ReadOptions`].
+///
+/// Only the limits on the size and compression ratio of the file apply, since the other limits
+/// concern the whole archive.
+pub fn read_zipfile_from_stream_with_options<'a, R: io::Read>(
+    reader: &'a mut R,
+    options: ReadOptions<'_>,
+) -> ZipResult<Option<ZipFile<'a>>> {
+    let signature = reader.read_u32::<LittleEndian>()?;
+
+    match signature {
+        spec::LOCAL_FILE_HEADER_SIGNATURE => (),
+        spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE => return Ok(None),
+        _ => return Err(ZipError::InvalidArchive("Invalid local file header")),
+    }
+
+    let mut result = parse_local_header(reader)?;
+    decode_names(std::slice::from_mut(&mut result), options.decoder);
+    options
+        .limits
+        .check_file_size(&result, result.uncompressed_size)?;
+
+    if result.encrypted {
         return unsupported_zip_error("Encrypted files are not supported");
     }
-    if using_data_descriptor {
+    if result.using_data_descriptor {
         return unsupported_zip_error("The file length is not available in the local header");
     }
 
-    let limit_reader = (reader as &'a mut dyn io::Read).take(result.compressed_size as u64);
+    let limit_reader = (Box::new(reader) as Box<dyn Read + 'a>).take(result.compressed_size as u64);
 
     let result_crc32 = result.crc32;
     let result_compression_method = result.compression_method;
+    let result_uncompressed_size = result.uncompressed_size;
+    #[cfg(feature = "legacy")]
+    let result_flags = result.flags;
     let crypto_reader = make_crypto_reader(
         result_compression_method,
         result_crc32,
@@ -1105,18 +2229,41 @@ pub fn read_zipfile_from_stream<'a, R: io::Read>(
         None,
         #[cfg(feature = "aes-crypto")]
         result.compressed_size,
+        false,
     )?
     .unwrap();
 
+    // The file was read from its local header
+    let local_extra_field = result.extra_field.clone();
     Ok(Some(ZipFile {
         data: Cow::Owned(result),
         crypto_reader: None,
-        reader: make_reader(result_compression_method, result_crc32, crypto_reader),
+        reader: make_reader(
+            result_compression_method,
+            result_crc32,
+            crypto_reader,
+            result_uncompressed_size,
+            #[cfg(feature = "legacy")]
+            result_flags,
+            #[cfg(any(feature = "lzma", feature = "xz"))]
+            options.limits.decompression_memory_limit(),
+            None,
+            #[cfg(feature = "zstd")]
+            None,
+        ),
+        codec: None,
+        #[cfg(feature = "zstd")]
+        zstd_dictionaries: None,
+        limits: options.limits,
+        bytes_read: 0,
+        local_extra_field,
     }))
 }
 
 #[cfg(test)]
 mod test {
+    use crate::extra_fields::ExtraField;
+
     #[test]
     fn invalid_offset() {
         use super::ZipArchive;
@@ -1162,6 +2309,79 @@ mod test {
         assert_eq!(reader.by_index(0).unwrap().central_header_start(), 77);
     }
 
+    #[test]
+    fn zip_seekable() {
+        use super::ZipArchive;
+        use std::io::{self, Read, Seek, SeekFrom};
+
+        let mut v = Vec::new();
+        v.extend_from_slice(include_bytes!("../tests/data/mimetype.zip"));
+        let mut reader = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        let mut file = reader.by_name_seekable("mimetype").unwrap();
+        assert_eq!(file.size(), 39);
+
+        let mut buf = [0; 4];
+        assert_eq!(file.seek(SeekFrom::Start(12)).unwrap(), 12);
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"vnd.");
+        assert_eq!(file.seek(SeekFrom::End(-4)).unwrap(), 35);
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"text");
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+        assert_eq!(file.seek(SeekFrom::Current(-27)).unwrap(), 12);
+        assert!(file.seek(SeekFrom::Current(-13)).is_err());
+
+        let mut contents = String::new();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "application/vnd.oasis.opendocument.text");
+    }
+
+    #[test]
+    fn zip_seekable_encrypted() {
+        use super::ZipArchive;
+        use crate::result::ZipError;
+        use std::io;
+
+        let mut v = Vec::new();
+        v.extend_from_slice(include_bytes!("../tests/data/aes_archive.zip"));
+        let mut reader = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        assert!(matches!(
+            reader.by_index_seekable(0),
+            Err(ZipError::UnsupportedArchive(_))
+        ));
+    }
+
+    #[test]
+    fn zip_slice() {
+        use super::ZipArchive;
+        use std::io;
+
+        let v = include_bytes!("../tests/data/mimetype.zip");
+        let reader = ZipArchive::new(io::Cursor::new(&v[..])).unwrap();
+        let contents = reader.by_name_slice("mimetype").unwrap();
+        assert_eq!(contents, b"application/vnd.oasis.opendocument.text");
+        assert_eq!(reader.by_index_raw_slice(0).unwrap(), contents);
+        assert!(reader.by_name_slice("nonexistent").is_err());
+    }
+
+    #[test]
+    fn zip_slice_compressed() {
+        use super::ZipArchive;
+        use crate::result::ZipError;
+        use std::io;
+
+        let v = include_bytes!("../tests/data/aes_archive.zip").to_vec();
+        let mut reader = ZipArchive::new(io::Cursor::new(v)).unwrap();
+        assert!(matches!(
+            reader.by_index_slice(0),
+            Err(ZipError::UnsupportedArchive(_))
+        ));
+        let compressed_size = reader.by_index_raw(0).unwrap().compressed_size();
+        let raw = reader.by_index_raw_slice(0).unwrap();
+        assert_eq!(raw.len() as u64, compressed_size);
+    }
+
     #[test]
     fn zip_read_streaming() {
         use super::read_zipfile_from_stream;
@@ -1237,4 +2457,65 @@ mod test {
             );
         }
     }
+
+    /// Build a central header with a name and comment that aren't flagged as UTF-8.
+    fn central_header(name: &[u8], comment: &[u8], extra_fields: &[ExtraField]) -> Vec<u8> {
+        use byteorder::{LittleEndian, WriteBytesExt};
+
+        let mut extra_field = Vec::new();
+        for field in extra_fields {
+            field.write_to(&mut extra_field).unwrap();
+        }
+        let mut header = Vec::new();
+        header.write_u32::<LittleEndian>(0x02014b50).unwrap();
+        header.extend_from_slice(&[0; 24]);
+        header.write_u16::<LittleEndian>(name.len() as u16).unwrap();
+        header
+            .write_u16::<LittleEndian>(extra_field.len() as u16)
+            .unwrap();
+        header
+            .write_u16::<LittleEndian>(comment.len() as u16)
+            .unwrap();
+        header.extend_from_slice(&[0; 12]);
+        header.extend_from_slice(name);
+        header.extend_from_slice(&extra_field);
+        header.extend_from_slice(comment);
+        header
+    }
+
+    #[test]
+    fn unicode_extra_fields() {
+        use super::{central_header_to_zip_file, decode_names, Cp437};
+        use std::io;
+
+        let read = |header: Vec<u8>| {
+            let mut file = central_header_to_zip_file(&mut io::Cursor::new(header), 0).unwrap();
+            decode_names(std::slice::from_mut(&mut file), &Cp437);
+            file
+        };
+
+        // "Grüße" in code page 850, which is decoded as CP437 without the extra fields
+        let name = b"Gr\x81\xe1e";
+        let comment = b"\x84";
+        let fields = [
+            ExtraField::UnicodePath {
+                crc32: crc32fast::hash(name),
+                name: "Grüße",
+            },
+            ExtraField::UnicodeComment {
+                crc32: crc32fast::hash(comment),
+                comment: "ä",
+            },
+        ];
+        let header = central_header(name, comment, &fields);
+        let file = read(header);
+        assert_eq!(file.file_name, "Grüße");
+        assert_eq!(file.file_comment, "ä");
+
+        // The fields don't belong to a name and comment that were changed afterwards
+        let header = central_header(b"other", b"changed", &fields);
+        let file = read(header);
+        assert_eq!(file.file_name, "other");
+        assert_eq!(file.file_comment, "changed");
+    }
 }
@@ -0,0 +1,138 @@
+//! Positional reads, so that the files in an archive can be read concurrently
+
+use crate::read::seek_position;
+use std::io::{self, Read, Seek};
+use std::sync::Arc;
+
+/// A source of data which can be read at any offset through a shared reference.
+///
+/// Unlike reading through [`Read`] and [`Seek`], reading at an offset doesn't move a cursor
+/// that is shared with other readers, so many threads can read at once. See [`ReadAtCursor`].
+pub trait ReadAt {
+    /// Read some bytes starting at `offset` into `buf`, returning how many bytes were read.
+    ///
+    /// Like [`Read::read`], this returns `Ok(0)` at the end of the data.
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize>;
+
+    /// Get the total size of the data, in bytes.
+    fn size(&self) -> io::Result<u64>;
+}
+
+impl ReadAt for [u8] {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        let start = offset.min(self.len() as u64) as usize;
+        let count = buf.len().min(self.len() - start);
+        buf[..count].copy_from_slice(&self[start..start + count]);
+        Ok(count)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+impl ReadAt for Vec<u8> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        self.as_slice().read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+}
+
+#[cfg(any(unix, windows))]
+impl ReadAt for std::fs::File {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        #[cfg(unix)]
+        return std::os::unix::fs::FileExt::read_at(self, buf, offset);
+        // This moves the file's cursor as well, which doesn't matter since it isn't used
+        #[cfg(windows)]
+        return std::os::windows::fs::FileExt::seek_read(self, buf, offset);
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for &T {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+impl<T: ReadAt + ?Sized> ReadAt for Arc<T> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+        (**self).read_at(buf, offset)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        (**self).size()
+    }
+}
+
+/// Reads from a [`ReadAt`] source through a cursor of its own.
+///
+/// Open an archive from a `ReadAtCursor` to read its files from several threads at once with
+/// [`ZipArchive::by_index_shared`](crate::ZipArchive::by_index_shared).
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use zip::read::ReadAtCursor;
+///
+/// fn count_bytes(file: std::fs::File) -> zip::result::ZipResult<u64> {
+///     let archive = Arc::new(zip::ZipArchive::new(ReadAtCursor::new(file))?);
+///     let threads: Vec<_> = (0..archive.len())
+///         .map(|i| {
+///             let archive = archive.clone();
+///             std::thread::spawn(move || -> zip::result::ZipResult<u64> {
+///                 let mut file = archive.by_index_shared(i)?;
+///                 Ok(std::io::copy(&mut file, &mut std::io::sink())?)
+///             })
+///         })
+///         .collect();
+///     threads.into_iter().map(|thread| thread.join().unwrap()).sum()
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReadAtCursor<T> {
+    inner: T,
+    position: u64,
+}
+
+impl<T> ReadAtCursor<T> {
+    /// Create a cursor at the start of `inner`.
+    pub fn new(inner: T) -> ReadAtCursor<T> {
+        ReadAtCursor { inner, position: 0 }
+    }
+
+    /// Get a reference to the underlying source
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Unwrap and return the underlying source
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T: ReadAt> Read for ReadAtCursor<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read_at(buf, self.position)?;
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl<T: ReadAt> Seek for ReadAtCursor<T> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let inner = &self.inner;
+        seek_position(&mut self.position, pos, || inner.size())
+    }
+}
@@ -0,0 +1,65 @@
+//! Options for reading existing archives
+
+use crate::encoding::{Cp437, NameDecoder};
+use crate::limits::Limits;
+
+/// Options for reading an existing archive
+///
+/// Used by [`ZipArchive::with_options`](crate::ZipArchive::with_options) and the other
+/// `_with_options` functions that read archives. The defaults match
+/// [`ZipArchive::new`](crate::ZipArchive::new).
+///
+/// ```no_run
+/// use zip::read::{Limits, ReadOptions};
+///
+/// fn open(file: std::fs::File) -> zip::result::ZipResult<zip::ZipArchive<std::fs::File>> {
+///     let decoder = |raw: &[u8]| String::from_utf8_lossy(raw).into_owned();
+///     let options = ReadOptions::default()
+///         .limits(Limits::default().max_files(10_000))
+///         .name_decoder(&decoder);
+///     zip::ZipArchive::with_options(file, options)
+/// }
+/// ```
+#[derive(Copy, Clone)]
+pub struct ReadOptions<'a> {
+    pub(crate) limits: Limits,
+    pub(crate) decoder: &'a dyn NameDecoder,
+    pub(crate) local_extra_fields: bool,
+}
+
+impl<'a> Default for ReadOptions<'a> {
+    fn default() -> Self {
+        ReadOptions {
+            limits: Limits::default(),
+            decoder: &Cp437,
+            local_extra_fields: false,
+        }
+    }
+}
+
+impl<'a> ReadOptions<'a> {
+    /// Set the limits to enforce on the archive and the files read from it. The default sets no
+    /// limits. See [`Limits`].
+    #[must_use]
+    pub fn limits(mut self, limits: Limits) -> ReadOptions<'a> {
+        self.limits = limits;
+        self
+    }
+
+    /// Set the decoder for the names and comments of files that aren't flagged as UTF-8. The
+    /// default decodes them as CP437. See [`NameDecoder`].
+    #[must_use]
+    pub fn name_decoder(mut self, decoder: &'a dyn NameDecoder) -> ReadOptions<'a> {
+        self.decoder = decoder;
+        self
+    }
+
+    /// Set whether to read the extra field of the local header when a file is opened, for
+    /// [`ZipFile::local_extra_fields`](crate::read::ZipFile::local_extra_fields). The default is
+    /// `false`, which skips it.
+    #[must_use]
+    pub fn local_extra_fields(mut self, read: bool) -> ReadOptions<'a> {
+        self.local_extra_fields = read;
+        self
+    }
+}
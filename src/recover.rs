@@ -0,0 +1,160 @@
+//! Recovery of archives whose central directory is missing or damaged
+
+use crate::read::parse_local_header;
+use crate::result::ZipResult;
+use crate::spec;
+use crate::types::ZipFileData;
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+use std::io::{self, Read, Seek};
+use std::ops::Range;
+
+/// The files found by scanning an archive for local headers
+pub(crate) struct Scan {
+    pub files: Vec<ZipFileData>,
+    /// Where the data of each file ends, including its data descriptor
+    pub ends: Vec<u64>,
+    /// Where the data of the last file ends
+    pub end: u64,
+    /// The regions between files that didn't contain a readable file
+    pub skipped: Vec<Range<u64>>,
+}
+
+/// Scan an archive from its start for local headers, skipping anything that can't be read.
+///
+/// Scanning stops at the central directory, if it follows a file directly.
+pub(crate) fn scan<R: Read + Seek>(reader: &mut R) -> ZipResult<Scan> {
+    let len = reader.seek(io::SeekFrom::End(0))?;
+    let mut files = Vec::new();
+    let mut ends = Vec::new();
+    let mut skipped = Vec::new();
+    // The end of the last file that was read, and where to search for the next one
+    let mut end = 0;
+    let mut search_start = 0;
+
+    loop {
+        if search_start == end && starts_central_directory(reader, end, len)? {
+            break;
+        }
+        let header_start =
+            match find_signature(reader, spec::LOCAL_FILE_HEADER_SIGNATURE, search_start, len)? {
+                Some(header_start) => header_start,
+                None => break,
+            };
+        match read_file(reader, header_start, len) {
+            Ok(Some((file, file_end))) => {
+                if header_start > end {
+                    skipped.push(end..header_start);
+                }
+                files.push(file);
+                ends.push(file_end);
+                end = file_end;
+                search_start = file_end;
+            }
+            Ok(None) | Err(_) => search_start = header_start + 1,
+        }
+    }
+    if end < len && !starts_central_directory(reader, end, len)? {
+        skipped.push(end..len);
+    }
+    Ok(Scan {
+        files,
+        ends,
+        end,
+        skipped,
+    })
+}
+
+fn starts_central_directory<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    len: u64,
+) -> io::Result<bool> {
+    if offset + 4 > len {
+        return Ok(false);
+    }
+    reader.seek(io::SeekFrom::Start(offset))?;
+    Ok(matches!(
+        reader.read_u32::<LittleEndian>()?,
+        spec::CENTRAL_DIRECTORY_HEADER_SIGNATURE | spec::ZIP64_CENTRAL_DIRECTORY_END_SIGNATURE
+    ))
+}
+
+/// Read the file whose local header starts at `header_start`, returning it and where it ends.
+/// Returns `None` if its data is truncated or its data descriptor can't be found.
+fn read_file<R: Read + Seek>(
+    reader: &mut R,
+    header_start: u64,
+    len: u64,
+) -> ZipResult<Option<(ZipFileData, u64)>> {
+    reader.seek(io::SeekFrom::Start(header_start + 4))?;
+    let mut file = parse_local_header(reader)?;
+    let data_start = reader.stream_position()?;
+    file.header_start = header_start;
+    file.data_start.store(data_start);
+
+    if !file.using_data_descriptor {
+        let end = data_start.checked_add(file.compressed_size);
+        return Ok(end.filter(|&end| end <= len).map(|end| (file, end)));
+    }
+
+    // The sizes are only stored after the data, so search for a data descriptor whose
+    // compressed size matches its distance from the start of the data. Only descriptors with
+    // the optional signature can be found this way.
+    let mut search_start = data_start;
+    while let Some(offset) =
+        find_signature(reader, spec::DATA_DESCRIPTOR_SIGNATURE, search_start, len)?
+    {
+        let compressed_size = offset - data_start;
+        let mut descriptor = [0; 20];
+        let available = ((len - offset - 4) as usize).min(descriptor.len());
+        reader.seek(io::SeekFrom::Start(offset + 4))?;
+        reader.read_exact(&mut descriptor[..available])?;
+        let sizes = if available >= 12
+            && LittleEndian::read_u32(&descriptor[4..8]) as u64 == compressed_size
+        {
+            Some((
+                LittleEndian::read_u32(&descriptor[8..12]) as u64,
+                offset + 16,
+            ))
+        } else if available >= 20 && LittleEndian::read_u64(&descriptor[4..12]) == compressed_size {
+            // ZIP64 descriptors have 8 byte sizes
+            Some((LittleEndian::read_u64(&descriptor[12..20]), offset + 24))
+        } else {
+            None
+        };
+        if let Some((uncompressed_size, end)) = sizes {
+            file.crc32 = LittleEndian::read_u32(&descriptor[0..4]);
+            file.compressed_size = compressed_size;
+            file.uncompressed_size = uncompressed_size;
+            return Ok(Some((file, end)));
+        }
+        search_start = offset + 1;
+    }
+    Ok(None)
+}
+
+/// Find the first occurrence of a signature at or after `start`.
+fn find_signature<R: Read + Seek>(
+    reader: &mut R,
+    signature: u32,
+    start: u64,
+    len: u64,
+) -> io::Result<Option<u64>> {
+    let signature = signature.to_le_bytes();
+    let mut buf = vec![0; 1 << 16];
+    let mut offset = start;
+    while offset + 4 <= len {
+        let count = ((len - offset) as usize).min(buf.len());
+        reader.seek(io::SeekFrom::Start(offset))?;
+        reader.read_exact(&mut buf[..count])?;
+        if let Some(i) = buf[..count]
+            .windows(4)
+            .position(|window| window == signature)
+        {
+            return Ok(Some(offset + i as u64));
+        }
+        // The signature may start in the last three bytes
+        offset += count as u64 - 3;
+    }
+    Ok(None)
+}
@@ -0,0 +1,52 @@
+//! Repair of damaged archives
+
+use crate::result::ZipError;
+use std::ops::Range;
+
+/// The result of [`ZipArchive::repair`](crate::ZipArchive::repair)
+#[derive(Debug)]
+pub struct RepairReport {
+    copied: Vec<String>,
+    failures: Vec<(String, ZipError)>,
+    skipped: Option<Vec<Range<u64>>>,
+}
+
+impl RepairReport {
+    pub(crate) fn new(skipped: Option<Vec<Range<u64>>>) -> RepairReport {
+        RepairReport {
+            copied: Vec::new(),
+            failures: Vec::new(),
+            skipped,
+        }
+    }
+
+    pub(crate) fn push_copied(&mut self, name: String) {
+        self.copied.push(name);
+    }
+
+    pub(crate) fn push_failure(&mut self, name: String, error: ZipError) {
+        self.failures.push((name, error));
+    }
+
+    /// Get the names of the files that were copied, in the order of the repaired archive
+    pub fn copied(&self) -> &[String] {
+        &self.copied
+    }
+
+    /// Get the names of the files that were found but couldn't be copied, and why
+    pub fn failures(&self) -> &[(String, ZipError)] {
+        &self.failures
+    }
+
+    /// Returns whether any files were recovered by scanning the archive, because its central
+    /// directory couldn't be read or didn't list them
+    pub fn scanned(&self) -> bool {
+        self.skipped.is_some()
+    }
+
+    /// Get the byte ranges of the damaged archive that were skipped while scanning it. This is
+    /// empty if no files were recovered by scanning.
+    pub fn skipped(&self) -> &[Range<u64>] {
+        self.skipped.as_deref().unwrap_or(&[])
+    }
+}
@@ -33,6 +33,11 @@ pub enum ZipError {
 
     /// The requested file could not be found in the archive
     FileNotFound,
+
+    /// A configured limit on the archive was exceeded, see [`Limits`](crate::read::Limits)
+    ///
+    /// Reading a file through [`std::io::Read`] reports this inside an [`io::Error`].
+    LimitExceeded(&'static str),
 }
 
 impl From<io::Error> for ZipError {
@@ -48,6 +53,7 @@ impl fmt::Display for ZipError {
             ZipError::InvalidArchive(err) => write!(fmt, "invalid Zip archive: {}", err),
             ZipError::UnsupportedArchive(err) => write!(fmt, "unsupported Zip archive: {}", err),
             ZipError::FileNotFound => write!(fmt, "specified file not found in archive"),
+            ZipError::LimitExceeded(err) => write!(fmt, "limit exceeded: {}", err),
         }
     }
 }
@@ -1,10 +1,16 @@
 //! Types that specify what is contained in a ZIP.
 
+use std::path::{Component, Path};
 use std::sync::atomic;
 
 #[cfg(feature = "time")]
 use time::{error::ComponentRange, Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
 
+mod ffi {
+    pub const S_IFDIR: u32 = 0o0040000;
+    pub const S_IFREG: u32 = 0o0100000;
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum System {
     Dos = 0,
@@ -237,6 +243,8 @@ pub struct ZipFileData {
     pub encrypted: bool,
     /// True if the file uses a data-descriptor section
     pub using_data_descriptor: bool,
+    /// General purpose bit flag as read from the archive
+    pub flags: u16,
     /// Compression method used to store the file
     pub compression_method: crate::compression::CompressionMethod,
     /// Last modified time. This will only have a 2 second precision.
@@ -255,6 +263,8 @@ pub struct ZipFileData {
     pub extra_field: Vec<u8>,
     /// File comment
     pub file_comment: String,
+    /// Raw file comment
+    pub file_comment_raw: Vec<u8>,
     /// Specifies where the local header of the file starts
     pub header_start: u64,
     /// Specifies where the central header of the file starts
@@ -299,6 +309,47 @@ impl ZipFileData {
             })
     }
 
+    pub fn enclosed_name(&self) -> Option<&Path> {
+        if self.file_name.contains('\0') {
+            return None;
+        }
+        let path = Path::new(&self.file_name);
+        let mut depth = 0usize;
+        for component in path.components() {
+            match component {
+                Component::Prefix(_) | Component::RootDir => return None,
+                Component::ParentDir => depth = depth.checked_sub(1)?,
+                Component::Normal(_) => depth += 1,
+                Component::CurDir => (),
+            }
+        }
+        Some(path)
+    }
+
+    pub fn unix_mode(&self) -> Option<u32> {
+        if self.external_attributes == 0 {
+            return None;
+        }
+
+        match self.system {
+            System::Unix => Some(self.external_attributes >> 16),
+            System::Dos => {
+                // Interpret MSDOS directory bit
+                let mut mode = if 0x10 == (self.external_attributes & 0x10) {
+                    ffi::S_IFDIR | 0o0775
+                } else {
+                    ffi::S_IFREG | 0o0664
+                };
+                if 0x01 == (self.external_attributes & 0x01) {
+                    // Read-only bit; strip write permissions
+                    mode &= 0o0555;
+                }
+                Some(mode)
+            }
+            _ => None,
+        }
+    }
+
     pub fn zip64_extension(&self) -> bool {
         self.uncompressed_size > 0xFFFFFFFF
             || self.compressed_size > 0xFFFFFFFF
@@ -308,9 +359,17 @@ impl ZipFileData {
     pub fn version_needed(&self) -> u16 {
         // higher versions matched first
         match (self.zip64_extension(), self.compression_method) {
+            #[cfg(feature = "lzma")]
+            (_, crate::compression::CompressionMethod::Lzma) => 63,
+            #[cfg(feature = "xz")]
+            (_, crate::compression::CompressionMethod::Xz) => 63,
+            #[cfg(feature = "ppmd")]
+            (_, crate::compression::CompressionMethod::Ppmd) => 63,
             #[cfg(feature = "bzip2")]
             (_, crate::compression::CompressionMethod::Bzip2) => 46,
             (true, _) => 45,
+            #[cfg(feature = "deflate64")]
+            (_, crate::compression::CompressionMethod::Deflate64) => 21,
             _ => 20,
         }
     }
@@ -320,26 +379,33 @@ impl ZipFileData {
 ///
 /// According to the [specification](https://www.winzip.com/win/en/aes_info.html#winzip11) AE-2
 /// does not make use of the CRC check.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AesVendorVersion {
+    /// AE-1, which stores the CRC-32 of the file
     Ae1,
+    /// AE-2, which stores no CRC-32
     Ae2,
 }
 
 /// AES variant used.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum AesMode {
+    /// AES with a 128 bit key
     Aes128,
+    /// AES with a 192 bit key
     Aes192,
+    /// AES with a 256 bit key
     Aes256,
 }
 
 #[cfg(feature = "aes-crypto")]
 impl AesMode {
+    /// Get the length of the salt, in bytes
     pub fn salt_length(&self) -> usize {
         self.key_length() / 2
     }
 
+    /// Get the length of the key, in bytes
     pub fn key_length(&self) -> usize {
         match self {
             Self::Aes128 => 16,
@@ -369,6 +435,7 @@ mod test {
             version_made_by: 0,
             encrypted: false,
             using_data_descriptor: false,
+            flags: 0,
             compression_method: crate::compression::CompressionMethod::Stored,
             last_modified_time: DateTime::default(),
             crc32: 0,
@@ -378,6 +445,7 @@ mod test {
             file_name_raw: file_name.into_bytes(),
             extra_field: Vec::new(),
             file_comment: String::new(),
+            file_comment_raw: Vec::new(),
             header_start: 0,
             data_start: AtomicU64::new(0),
             central_header_start: 0,
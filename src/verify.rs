@@ -0,0 +1,173 @@
+//! Integrity checks for whole archives
+
+use crate::read::parse_local_header;
+use crate::result::{ZipError, ZipResult};
+use crate::spec;
+use crate::types::ZipFileData;
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{self, Read, Seek};
+
+/// A problem found with a file by [`ZipArchive::verify`](crate::ZipArchive::verify)
+#[derive(Debug)]
+pub enum VerifyProblem {
+    /// A field of the local header differs from the central directory
+    LocalHeaderMismatch(&'static str),
+    /// A field of the data descriptor differs from the central directory
+    DataDescriptorMismatch(&'static str),
+    /// The file decompressed to a different size than the central directory states
+    SizeMismatch,
+    /// The password didn't match the file
+    InvalidPassword,
+    /// The file couldn't be read, e.g. because its CRC-32 or authentication code is wrong or its
+    /// compression method is unsupported
+    Error(ZipError),
+}
+
+/// The result of verifying a single file
+#[derive(Debug)]
+pub struct FileReport {
+    index: usize,
+    name: String,
+    problems: Vec<VerifyProblem>,
+}
+
+impl FileReport {
+    pub(crate) fn new(index: usize, name: String, problems: Vec<VerifyProblem>) -> FileReport {
+        FileReport {
+            index,
+            name,
+            problems,
+        }
+    }
+
+    /// Get the index of the file in the archive
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the name of the file
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get the problems found with the file
+    pub fn problems(&self) -> &[VerifyProblem] {
+        &self.problems
+    }
+
+    /// Returns whether no problems were found with the file
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// The result of [`ZipArchive::verify`](crate::ZipArchive::verify), with a report for every
+/// file in the archive
+#[derive(Debug)]
+pub struct VerifyReport {
+    files: Vec<FileReport>,
+}
+
+impl VerifyReport {
+    pub(crate) fn new(files: Vec<FileReport>) -> VerifyReport {
+        VerifyReport { files }
+    }
+
+    /// Get the reports of all files, in the order of the archive
+    pub fn files(&self) -> &[FileReport] {
+        &self.files
+    }
+
+    /// Get the reports of the files with problems
+    pub fn failures(&self) -> impl Iterator<Item = &FileReport> {
+        self.files.iter().filter(|file| !file.is_ok())
+    }
+
+    /// Returns whether no problems were found with any file
+    pub fn is_ok(&self) -> bool {
+        self.files.iter().all(FileReport::is_ok)
+    }
+}
+
+/// Compare the local header and data descriptor of a file with its central directory entry.
+pub(crate) fn check_headers<R: Read + Seek>(
+    central: &ZipFileData,
+    reader: &mut R,
+    problems: &mut Vec<VerifyProblem>,
+) -> ZipResult<()> {
+    reader.seek(io::SeekFrom::Start(central.header_start))?;
+    if reader.read_u32::<LittleEndian>()? != spec::LOCAL_FILE_HEADER_SIGNATURE {
+        return Err(ZipError::InvalidArchive("Invalid local file header"));
+    }
+    let local = parse_local_header(reader)?;
+
+    let mut mismatch = |field: &'static str, equal: bool| {
+        if !equal {
+            problems.push(VerifyProblem::LocalHeaderMismatch(field));
+        }
+    };
+    mismatch("file name", local.file_name_raw == central.file_name_raw);
+    mismatch(
+        "compression method",
+        local.compression_method == central.compression_method,
+    );
+    mismatch("flags", local.flags == central.flags);
+    if local.using_data_descriptor {
+        // The CRC-32 and sizes in the local header are unused, and are usually zero
+        return check_data_descriptor(central, local.large_file, reader, problems);
+    }
+    mismatch("CRC-32", local.crc32 == central.crc32);
+    mismatch(
+        "compressed size",
+        local.compressed_size == central.compressed_size,
+    );
+    mismatch(
+        "uncompressed size",
+        local.uncompressed_size == central.uncompressed_size,
+    );
+    Ok(())
+}
+
+/// Compare the data descriptor following the data of a file with its central directory entry.
+/// The reader must be at the start of the data.
+fn check_data_descriptor<R: Read + Seek>(
+    central: &ZipFileData,
+    large_file: bool,
+    reader: &mut R,
+    problems: &mut Vec<VerifyProblem>,
+) -> ZipResult<()> {
+    reader.seek(io::SeekFrom::Current(central.compressed_size as i64))?;
+    // The signature is optional
+    let mut crc32 = reader.read_u32::<LittleEndian>()?;
+    if crc32 == spec::DATA_DESCRIPTOR_SIGNATURE {
+        crc32 = reader.read_u32::<LittleEndian>()?;
+    }
+    // Sizes are stored with 8 bytes in ZIP64 archives
+    let (compressed_size, uncompressed_size) = if large_file || central.large_file {
+        (
+            reader.read_u64::<LittleEndian>()?,
+            reader.read_u64::<LittleEndian>()?,
+        )
+    } else {
+        (
+            reader.read_u32::<LittleEndian>()? as u64,
+            reader.read_u32::<LittleEndian>()? as u64,
+        )
+    };
+
+    let mut mismatch = |field: &'static str, equal: bool| {
+        if !equal {
+            problems.push(VerifyProblem::DataDescriptorMismatch(field));
+        }
+    };
+    mismatch("CRC-32", crc32 == central.crc32);
+    mismatch(
+        "compressed size",
+        compressed_size == central.compressed_size,
+    );
+    mismatch(
+        "uncompressed size",
+        uncompressed_size == central.uncompressed_size,
+    );
+    Ok(())
+}
@@ -1,16 +1,21 @@
 //! Types for creating ZIP archives
 
+use crate::codec::{Codec, CodecWriter, Codecs};
 use crate::compression::CompressionMethod;
-use crate::read::{central_header_to_zip_file, ZipArchive, ZipFile};
+use crate::cp437::{to_cp437, FromCp437};
+use crate::extra_fields::ExtraField;
+use crate::read::{central_header_to_zip_file, decode_names, ReadOptions, ZipArchive, ZipFile};
 use crate::result::{ZipError, ZipResult};
 use crate::spec;
 use crate::types::{AtomicU64, DateTime, System, ZipFileData, DEFAULT_VERSION};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use crc32fast::Hasher;
+use std::convert::TryFrom;
 use std::default::Default;
 use std::io;
 use std::io::prelude::*;
 use std::mem;
+use std::sync::Arc;
 
 #[cfg(any(
     feature = "deflate",
@@ -22,11 +27,21 @@ use flate2::write::DeflateEncoder;
 #[cfg(feature = "bzip2")]
 use bzip2::write::BzEncoder;
 
+#[cfg(feature = "lzma")]
+use crate::lzma::LzmaWriter;
+#[cfg(feature = "ppmd")]
+use crate::ppmd::PpmdWriter;
+
 #[cfg(feature = "time")]
 use time::OffsetDateTime;
 
+#[cfg(feature = "xz")]
+use xz2::write::XzEncoder;
+
 #[cfg(feature = "zstd")]
-use zstd::stream::write::Encoder as ZstdEncoder;
+use zstd::stream::raw::{Encoder as ZstdEncoder, Operation};
+#[cfg(feature = "zstd")]
+use zstd::stream::zio::Writer as ZstdWriter;
 
 enum GenericZipWriter<W: Write + io::Seek> {
     Closed,
@@ -39,8 +54,15 @@ enum GenericZipWriter<W: Write + io::Seek> {
     Deflater(DeflateEncoder<W>),
     #[cfg(feature = "bzip2")]
     Bzip2(BzEncoder<W>),
+    #[cfg(feature = "lzma")]
+    Lzma(LzmaWriter<W>),
     #[cfg(feature = "zstd")]
-    Zstd(ZstdEncoder<'static, W>),
+    Zstd(ZstdWriter<W, ZstdEncoder<'static>>),
+    #[cfg(feature = "xz")]
+    Xz(XzEncoder<W>),
+    #[cfg(feature = "ppmd")]
+    Ppmd(PpmdWriter<W>),
+    Custom(CodecWriter<W>),
 }
 
 /// ZIP archive generator
@@ -80,6 +102,16 @@ pub struct ZipWriter<W: Write + io::Seek> {
     writing_to_central_extra_field_only: bool,
     writing_raw: bool,
     comment: Vec<u8>,
+    /// The uncompressed data of the current file, while it's buffered to decide whether to
+    /// store it instead, see [`FileOptions::store_if_incompressible`]
+    stored_fallback: Option<Vec<u8>>,
+    /// The most data of the current file to buffer for the stored fallback
+    max_stored_fallback: usize,
+    codecs: Codecs,
+    /// An idle Zstandard encoder with the current dictionary loaded, which is reused for the
+    /// next file. `None` while a file uses it, or if there's no dictionary.
+    #[cfg(feature = "zstd")]
+    zstd_encoder: Option<ZstdEncoder<'static>>,
 }
 
 #[derive(Default)]
@@ -93,6 +125,33 @@ struct ZipRawValues {
     crc32: u32,
     compressed_size: u64,
     uncompressed_size: u64,
+    /// The bits of the general purpose flag that depend on the compression method
+    method_flags: u16,
+}
+
+/// The bits of the general purpose flag that depend on the compression method, such as the
+/// dictionary size of imploded files or the end-of-stream marker of LZMA files
+const METHOD_FLAGS: u16 = 0b110;
+
+/// The name of a new file, and the bytes it's written as
+struct EntryName {
+    name: String,
+    raw: Vec<u8>,
+    utf8: bool,
+}
+
+impl EntryName {
+    fn new(name: String, cp437: bool) -> EntryName {
+        let (raw, utf8) = if name.is_ascii() {
+            (name.as_bytes().to_vec(), false)
+        } else {
+            match to_cp437(&name).filter(|_| cp437) {
+                Some(raw) => (raw, false),
+                None => (name.as_bytes().to_vec(), true),
+            }
+        };
+        EntryName { name, raw, utf8 }
+    }
 }
 
 /// Metadata for a file to be written
@@ -102,6 +161,8 @@ pub struct FileOptions {
     last_modified_time: DateTime,
     permissions: Option<u32>,
     large_file: bool,
+    store_if_incompressible: Option<usize>,
+    cp437_name: bool,
 }
 
 impl FileOptions {
@@ -126,6 +187,8 @@ impl FileOptions {
             last_modified_time: DateTime::default(),
             permissions: None,
             large_file: false,
+            store_if_incompressible: None,
+            cp437_name: false,
         }
     }
 
@@ -170,6 +233,34 @@ impl FileOptions {
         self.large_file = large;
         self
     }
+
+    /// Set whether the new file should be stored uncompressed if compressing it doesn't make it
+    /// smaller, buffering at most `max_buffer` bytes of it to find out.
+    ///
+    /// When enabled, the contents of the file are buffered in memory while it's written. When the
+    /// file is finished, the buffered contents are compressed, and written as
+    /// [`CompressionMethod::Stored`] if compressing them doesn't make them smaller. Files larger
+    /// than `max_buffer` are compressed as usual once they exceed it, so that no more is
+    /// buffered. This is useful for data that is already compressed, such as JPEG or PNG images.
+    /// The default is `None`, which always compresses the file.
+    #[must_use]
+    pub fn store_if_incompressible(mut self, max_buffer: Option<usize>) -> FileOptions {
+        self.store_if_incompressible = max_buffer;
+        self
+    }
+
+    /// Set whether the name of the new file should be encoded as IBM code page 437 when all of
+    /// its characters can be represented in it.
+    ///
+    /// By default, names that aren't ASCII are written as UTF-8 and flagged as such, which some
+    /// older tools and devices can't read. With this option, they are written in CP437 without
+    /// the UTF-8 flag instead, and names that CP437 can't represent are still written as UTF-8.
+    /// The default is `false`.
+    #[must_use]
+    pub fn cp437_name(mut self, cp437: bool) -> FileOptions {
+        self.cp437_name = cp437;
+        self
+    }
 }
 
 impl Default for FileOptions {
@@ -186,6 +277,21 @@ impl<W: Write + io::Seek> Write for ZipWriter<W> {
                 "No file has been started",
             ));
         }
+        if !self.writing_to_extra_field {
+            if let Some(ref mut uncompressed) = self.stored_fallback {
+                if uncompressed.len() + buf.len() <= self.max_stored_fallback {
+                    uncompressed.extend_from_slice(buf);
+                    self.stats.update(buf);
+                    return Ok(buf.len());
+                }
+                // The file is too large to buffer, so compress it as usual
+                let uncompressed = self.stored_fallback.take().unwrap();
+                self.start_data(self.files.last().unwrap().compression_method)?;
+                if let Some(w) = self.inner.ref_mut() {
+                    w.write_all(&uncompressed)?;
+                }
+            }
+        }
         match self.inner.ref_mut() {
             Some(ref mut w) => {
                 if self.writing_to_extra_field {
@@ -234,7 +340,20 @@ impl ZipWriterStats {
 
 impl<A: Read + Write + io::Seek> ZipWriter<A> {
     /// Initializes the archive from an existing ZIP archive, making it ready for append.
-    pub fn new_append(mut readwriter: A) -> ZipResult<ZipWriter<A>> {
+    pub fn new_append(readwriter: A) -> ZipResult<ZipWriter<A>> {
+        Self::new_append_with_options(readwriter, ReadOptions::default())
+    }
+
+    /// Initializes the archive from an existing ZIP archive like [`ZipWriter::new_append`],
+    /// reading it with the given options. See [`ReadOptions`].
+    ///
+    /// The limits on the number of files, the size of the central directory and the total
+    /// size of the files are checked for the existing files.
+    pub fn new_append_with_options(
+        mut readwriter: A,
+        options: ReadOptions<'_>,
+    ) -> ZipResult<ZipWriter<A>> {
+        let limits = options.limits;
         let (footer, cde_start_pos) = spec::CentralDirectoryEnd::find_and_parse(&mut readwriter)?;
 
         if footer.disk_number != footer.disk_with_central_directory {
@@ -255,9 +374,18 @@ impl<A: Read + Write + io::Seek> ZipWriter<A> {
             ));
         }
 
-        let files = (0..number_of_files)
-            .map(|_| central_header_to_zip_file(&mut readwriter, archive_offset))
-            .collect::<Result<Vec<_>, _>>()?;
+        limits.check_files(number_of_files)?;
+        let mut files = Vec::new();
+        let mut total_size = 0u64;
+        for _ in 0..number_of_files {
+            let file = central_header_to_zip_file(&mut readwriter, archive_offset)?;
+            let position = readwriter.stream_position()?;
+            limits.check_central_directory_size(position.saturating_sub(directory_start))?;
+            total_size = total_size.saturating_add(file.uncompressed_size);
+            limits.check_total_size(total_size)?;
+            files.push(file);
+        }
+        decode_names(&mut files, options.decoder);
 
         let _ = readwriter.seek(io::SeekFrom::Start(directory_start)); // seek directory_start to overwrite it
 
@@ -270,10 +398,153 @@ impl<A: Read + Write + io::Seek> ZipWriter<A> {
             writing_to_central_extra_field_only: false,
             comment: footer.zip_file_comment,
             writing_raw: true, // avoid recomputing the last file's header
+            stored_fallback: None,
+            max_stored_fallback: 0,
+            codecs: Codecs::default(),
+            #[cfg(feature = "zstd")]
+            zstd_encoder: None,
+        })
+    }
+}
+
+/// A writer whose data can be cut off at a given length, which [`ZipWriter::resume`] uses to
+/// discard incomplete files
+pub trait Truncate {
+    /// Truncate the data to `size` bytes, or extend it with zeros if it's shorter.
+    fn set_len(&mut self, size: u64) -> io::Result<()>;
+}
+
+impl Truncate for std::fs::File {
+    fn set_len(&mut self, size: u64) -> io::Result<()> {
+        std::fs::File::set_len(self, size)
+    }
+}
+
+impl Truncate for io::Cursor<Vec<u8>> {
+    fn set_len(&mut self, size: u64) -> io::Result<()> {
+        resize(self.get_mut(), size)
+    }
+}
+
+impl Truncate for io::Cursor<&mut Vec<u8>> {
+    fn set_len(&mut self, size: u64) -> io::Result<()> {
+        resize(self.get_mut(), size)
+    }
+}
+
+impl<T: Truncate + ?Sized> Truncate for &mut T {
+    fn set_len(&mut self, size: u64) -> io::Result<()> {
+        (**self).set_len(size)
+    }
+}
+
+fn resize(data: &mut Vec<u8>, size: u64) -> io::Result<()> {
+    let size = usize::try_from(size)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "size is too large"))?;
+    data.resize(size, 0);
+    Ok(())
+}
+
+impl<A: Read + Write + io::Seek + Truncate> ZipWriter<A> {
+    /// Initializes the archive from the output of a `ZipWriter` that wasn't finished, e.g.
+    /// because the process writing it crashed, making it ready for append.
+    ///
+    /// Such an archive has no central directory, so it can't be opened with
+    /// [`ZipWriter::new_append`]. Instead, the local headers of its files are scanned as with
+    /// [`ZipArchive::recover`], and every file is checked by decompressing it. The files are
+    /// kept up to the first one that is damaged or incomplete, or that doesn't directly follow
+    /// the previous one. The archive is truncated after the last file that is kept, which
+    /// discards everything after it, even files that could be read. New files are written from
+    /// there, and finishing the archive writes a central directory for all files.
+    ///
+    /// Encrypted files can't be decompressed, so only their sizes are checked. The unix
+    /// permissions of the existing files are lost, since they're only stored in the central
+    /// directory.
+    pub fn resume(readwriter: A) -> ZipResult<ZipWriter<A>> {
+        Self::resume_with_options(readwriter, ReadOptions::default())
+    }
+
+    /// Initializes the archive from the output of a `ZipWriter` that wasn't finished like
+    /// [`ZipWriter::resume`], reading it with the given options. See [`ReadOptions`].
+    ///
+    /// The limits on the number of files and the total size of the files are checked for the
+    /// scanned files.
+    pub fn resume_with_options(
+        mut readwriter: A,
+        options: ReadOptions<'_>,
+    ) -> ZipResult<ZipWriter<A>> {
+        let mut scan = crate::recover::scan(&mut readwriter)?;
+        options.limits.check_scanned_files(&scan.files)?;
+        decode_names(&mut scan.files, options.decoder);
+        let len = readwriter.seek(io::SeekFrom::End(0))?;
+
+        let mut files = Vec::new();
+        let mut end = scan.files.first().map_or(0, |file| file.header_start);
+        let mut scanned = scan.files.into_iter().zip(scan.ends).peekable();
+        while let Some((file, file_end)) = scanned.next() {
+            if file.header_start != end {
+                break;
+            }
+            // The local header is only updated once the file is finished, so an unfinished
+            // file appears to be empty, and is followed by its data instead of another file
+            let next_start = scanned.peek().map(|(next, _)| next.header_start);
+            let unfinished = file.crc32 == 0
+                && file.compressed_size == 0
+                && file.uncompressed_size == 0
+                && file_end != len
+                && next_start != Some(file_end);
+            let damaged =
+                !file.encrypted && crate::read::check_file_data(&file, &mut readwriter).is_err();
+            if unfinished || damaged {
+                break;
+            }
+            files.push(file);
+            end = file_end;
+        }
+        for file in files.iter_mut() {
+            // The ZIP64 extra field is written separately for the central directory
+            file.extra_field = strip_zip64_extra_field(&file.extra_field)?;
+        }
+
+        readwriter.set_len(end)?;
+        readwriter.seek(io::SeekFrom::Start(end))?;
+
+        Ok(ZipWriter {
+            inner: GenericZipWriter::Storer(readwriter),
+            files,
+            stats: Default::default(),
+            writing_to_file: false,
+            writing_to_extra_field: false,
+            writing_to_central_extra_field_only: false,
+            comment: Vec::new(),
+            writing_raw: true, // avoid recomputing the last file's header
+            stored_fallback: None,
+            max_stored_fallback: 0,
+            codecs: Codecs::default(),
+            #[cfg(feature = "zstd")]
+            zstd_encoder: None,
         })
     }
 }
 
+/// Remove the ZIP64 extended information from an extra field.
+fn strip_zip64_extra_field(mut data: &[u8]) -> ZipResult<Vec<u8>> {
+    let mut stripped = Vec::with_capacity(data.len());
+    while data.len() >= 4 {
+        let block = data;
+        let kind = data.read_u16::<LittleEndian>()?;
+        let size = data.read_u16::<LittleEndian>()? as usize;
+        if size > data.len() {
+            return Err(ZipError::InvalidArchive("Invalid extra field"));
+        }
+        if kind != 0x0001 {
+            stripped.extend_from_slice(&block[..4 + size]);
+        }
+        data = &data[size..];
+    }
+    Ok(stripped)
+}
+
 impl<W: Write + io::Seek> ZipWriter<W> {
     /// Initializes the archive.
     ///
@@ -288,6 +559,11 @@ impl<W: Write + io::Seek> ZipWriter<W> {
             writing_to_central_extra_field_only: false,
             writing_raw: false,
             comment: Vec::new(),
+            stored_fallback: None,
+            max_stored_fallback: 0,
+            codecs: Codecs::default(),
+            #[cfg(feature = "zstd")]
+            zstd_encoder: None,
         }
     }
 
@@ -307,6 +583,29 @@ impl<W: Write + io::Seek> ZipWriter<W> {
         self.comment = comment;
     }
 
+    /// Register a codec to compress files with the method `method`.
+    ///
+    /// Files whose [`FileOptions::compression_method`] has the ID `method` are compressed by the
+    /// codec instead of the built-in implementation of the method, if there is one. Registering
+    /// another codec for the same method replaces the previous one. See the
+    /// [`codec`](crate::codec) module.
+    pub fn register_codec(&mut self, method: u16, codec: impl Codec + 'static) {
+        self.codecs.insert(method, Arc::new(codec));
+    }
+
+    /// Set a Zstandard dictionary to compress the following zstd files with.
+    ///
+    /// The dictionary is prepared once and used by every file that is started with
+    /// [`CompressionMethod::Zstd`] until another dictionary is set. An empty dictionary compresses
+    /// the following files without one. The dictionary isn't stored in the archive, so readers
+    /// need it too, see
+    /// [`ZipArchive::add_zstd_dictionary`](crate::read::ZipArchive::add_zstd_dictionary).
+    #[cfg(feature = "zstd")]
+    pub fn set_zstd_dictionary(&mut self, dictionary: &[u8]) -> ZipResult<()> {
+        self.zstd_encoder = Some(ZstdEncoder::with_dictionary(0, dictionary)?);
+        Ok(())
+    }
+
     /// Start a new file for with the requested options.
     fn start_entry<S>(
         &mut self,
@@ -317,12 +616,51 @@ impl<W: Write + io::Seek> ZipWriter<W> {
     where
         S: Into<String>,
     {
+        let name = EntryName::new(name.into(), options.cp437_name);
+        self.start_named_entry(name, options, raw_values, &[], &[])
+    }
+
+    fn start_named_entry(
+        &mut self,
+        name: EntryName,
+        options: FileOptions,
+        raw_values: Option<ZipRawValues>,
+        local_fields: &[ExtraField],
+        central_fields: &[ExtraField],
+    ) -> ZipResult<()> {
         self.finish_file()?;
 
+        self.stored_fallback = match options.store_if_incompressible {
+            Some(max_buffer)
+                if options.compression_method != CompressionMethod::Stored
+                    && raw_values.is_none() =>
+            {
+                self.max_stored_fallback = max_buffer;
+                Some(Vec::new())
+            }
+            _ => None,
+        };
+
+        // The ZIP64 extended information is 20 bytes in the local header, and up to 28 bytes
+        // in the central header
+        let local_zip64_size = if options.large_file { 20 } else { 0 };
+        let local_extra_field = encode_extra_fields(local_fields, local_zip64_size)?;
+        let central_extra_field = encode_extra_fields(central_fields, 28)?;
+
+        // The LZMA stream of a new file ends with an end-of-stream marker
+        #[cfg(feature = "lzma")]
+        let method_flags = if options.compression_method == CompressionMethod::Lzma {
+            1 << 1
+        } else {
+            0
+        };
+        #[cfg(not(feature = "lzma"))]
+        let method_flags = 0;
         let raw_values = raw_values.unwrap_or(ZipRawValues {
             crc32: 0,
             compressed_size: 0,
             uncompressed_size: 0,
+            method_flags,
         });
 
         {
@@ -335,15 +673,17 @@ impl<W: Write + io::Seek> ZipWriter<W> {
                 version_made_by: DEFAULT_VERSION,
                 encrypted: false,
                 using_data_descriptor: false,
+                flags: if name.utf8 { 1u16 << 11 } else { 0 } | raw_values.method_flags,
                 compression_method: options.compression_method,
                 last_modified_time: options.last_modified_time,
                 crc32: raw_values.crc32,
                 compressed_size: raw_values.compressed_size,
                 uncompressed_size: raw_values.uncompressed_size,
-                file_name: name.into(),
-                file_name_raw: Vec::new(), // Never used for saving
-                extra_field: Vec::new(),
+                file_name: name.name,
+                file_name_raw: name.raw,
+                extra_field: local_extra_field,
                 file_comment: String::new(),
+                file_comment_raw: Vec::new(),
                 header_start,
                 data_start: AtomicU64::new(0),
                 central_header_start: 0,
@@ -352,6 +692,7 @@ impl<W: Write + io::Seek> ZipWriter<W> {
                 aes_mode: None,
             };
             write_local_file_header(writer, &file)?;
+            file.extra_field = central_extra_field;
 
             let header_end = writer.seek(io::SeekFrom::Current(0))?;
             self.stats.start = header_end;
@@ -371,7 +712,15 @@ impl<W: Write + io::Seek> ZipWriter<W> {
             // Implicitly calling [`ZipWriter::end_extra_data`] for empty files.
             self.end_extra_data()?;
         }
-        self.inner.switch_to(CompressionMethod::Stored)?;
+        if let Some(uncompressed) = self.stored_fallback.take() {
+            self.write_buffered(&uncompressed)?;
+        }
+        self.inner.switch_to(
+            CompressionMethod::Stored,
+            None,
+            #[cfg(feature = "zstd")]
+            &mut self.zstd_encoder,
+        )?;
         let writer = self.inner.get_plain();
 
         if !self.writing_raw {
@@ -394,19 +743,162 @@ impl<W: Write + io::Seek> ZipWriter<W> {
         Ok(())
     }
 
+    /// Switch to the compression method of the current file before writing its data, unless the
+    /// data is buffered for the stored fallback.
+    fn start_data(&mut self, method: CompressionMethod) -> ZipResult<()> {
+        if self.stored_fallback.is_some() {
+            return Ok(());
+        }
+        self.inner.switch_to(
+            method,
+            self.codecs.get(method),
+            #[cfg(feature = "zstd")]
+            &mut self.zstd_encoder,
+        )
+    }
+
+    /// Write the buffered data of the current file, compressed if that makes it smaller and
+    /// stored otherwise.
+    fn write_buffered(&mut self, uncompressed: &[u8]) -> ZipResult<()> {
+        let file = self.files.last_mut().unwrap();
+        let mut compressor = GenericZipWriter::Storer(io::Cursor::new(Vec::new()));
+        compressor.switch_to(
+            file.compression_method,
+            self.codecs.get(file.compression_method),
+            #[cfg(feature = "zstd")]
+            &mut self.zstd_encoder,
+        )?;
+        if let Some(w) = compressor.ref_mut() {
+            w.write_all(uncompressed)?;
+        }
+        compressor.switch_to(
+            CompressionMethod::Stored,
+            None,
+            #[cfg(feature = "zstd")]
+            &mut self.zstd_encoder,
+        )?;
+        let compressed = compressor.unwrap().into_inner();
+
+        let writer = self.inner.get_plain();
+        if compressed.len() < uncompressed.len() {
+            writer.write_all(&compressed)?;
+            return Ok(());
+        }
+        // Compression didn't help, so store the data. The header keeps its size, so only the
+        // fields that depend on the method need patching.
+        file.compression_method = CompressionMethod::Stored;
+        file.flags &= !METHOD_FLAGS;
+        writer.seek(io::SeekFrom::Start(file.header_start + 4))?;
+        writer.write_u16::<LittleEndian>(file.version_needed())?;
+        writer.write_u16::<LittleEndian>(general_purpose_flag(file))?;
+        #[allow(deprecated)]
+        writer.write_u16::<LittleEndian>(file.compression_method.to_u16())?;
+        writer.seek(io::SeekFrom::Start(self.stats.start))?;
+        writer.write_all(uncompressed)?;
+        Ok(())
+    }
+
     /// Create a file in the archive and start writing its' contents.
     ///
     /// The data should be written using the [`io::Write`] implementation on this [`ZipWriter`]
-    pub fn start_file<S>(&mut self, name: S, mut options: FileOptions) -> ZipResult<()>
+    pub fn start_file<S>(&mut self, name: S, options: FileOptions) -> ZipResult<()>
     where
         S: Into<String>,
     {
+        let name = EntryName::new(name.into(), options.cp437_name);
+        self.start_named_file(name, options, &[], &[])
+    }
+
+    /// Create a file with extra fields in its local and central headers, and start writing its
+    /// contents.
+    ///
+    /// Records of kinds that aren't known to [`ExtraField`] can be added as
+    /// [`ExtraField::Unknown`]. The ZIP64 extended information is added by the writer itself
+    /// when needed, so [`ExtraField::Zip64`] is rejected, as is [`ExtraField::Aes`] since files
+    /// can't be written with AES encryption. Starting the file fails if a header's extra field
+    /// exceeds 64 KiB together with the ZIP64 extended information.
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use zip::extra_fields::ExtraField;
+    /// use zip::write::FileOptions;
+    ///
+    /// let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    /// let fields = [ExtraField::UnixOwner { uid: 1000, gid: 1000 }];
+    /// zip.start_file_with_extra_fields("a.txt", FileOptions::default(), &fields, &fields)?;
+    /// zip.write_all(b"Hello, World!")?;
+    /// zip.finish()?;
+    /// # Ok::<(), zip::result::ZipError>(())
+    /// ```
+    pub fn start_file_with_extra_fields<S>(
+        &mut self,
+        name: S,
+        options: FileOptions,
+        local_fields: &[ExtraField],
+        central_fields: &[ExtraField],
+    ) -> ZipResult<()>
+    where
+        S: Into<String>,
+    {
+        let name = EntryName::new(name.into(), options.cp437_name);
+        self.start_named_file(name, options, local_fields, central_fields)
+    }
+
+    /// Create a file whose name is written to the archive as the given bytes, and start writing
+    /// its contents. The extra fields are added to its local and central headers as with
+    /// [`ZipWriter::start_file_with_extra_fields`].
+    ///
+    /// The name isn't flagged as UTF-8, so readers decode it as CP437 or in the code page of the
+    /// system they run on. This allows writing names in the legacy encoding that a consumer
+    /// expects. To record the name for readers that support Unicode as well, add an
+    /// [`ExtraField::unicode_path`] record.
+    ///
+    /// ```
+    /// use std::io::Write;
+    /// use zip::extra_fields::ExtraField;
+    /// use zip::write::FileOptions;
+    ///
+    /// let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    /// // "日本語.txt" in Shift-JIS
+    /// let raw_name = b"\x93\xfa\x96\x7b\x8c\xea.txt";
+    /// let fields = [ExtraField::unicode_path(raw_name, "日本語.txt")];
+    /// zip.start_file_raw_name(&raw_name[..], FileOptions::default(), &fields, &fields)?;
+    /// zip.write_all(b"Hello, World!")?;
+    /// zip.finish()?;
+    /// # Ok::<(), zip::result::ZipError>(())
+    /// ```
+    pub fn start_file_raw_name<N>(
+        &mut self,
+        name: N,
+        options: FileOptions,
+        local_fields: &[ExtraField],
+        central_fields: &[ExtraField],
+    ) -> ZipResult<()>
+    where
+        N: Into<Vec<u8>>,
+    {
+        let raw = name.into();
+        let name = EntryName {
+            name: raw.as_slice().from_cp437().into_owned(),
+            raw,
+            utf8: false,
+        };
+        self.start_named_file(name, options, local_fields, central_fields)
+    }
+
+    fn start_named_file(
+        &mut self,
+        name: EntryName,
+        mut options: FileOptions,
+        local_fields: &[ExtraField],
+        central_fields: &[ExtraField],
+    ) -> ZipResult<()> {
         if options.permissions.is_none() {
             options.permissions = Some(0o644);
         }
         *options.permissions.as_mut().unwrap() |= 0o100000;
-        self.start_entry(name, options, None)?;
-        self.inner.switch_to(options.compression_method)?;
+        self.start_named_entry(name, options, None, local_fields, central_fields)?;
+        self.start_data(options.compression_method)?;
         self.writing_to_file = true;
         Ok(())
     }
@@ -561,7 +1053,7 @@ impl<W: Write + io::Seek> ZipWriter<W> {
         }
         let file = self.files.last_mut().unwrap();
 
-        validate_extra_data(file)?;
+        validate_extra_data(&file.extra_field)?;
 
         let data_start = file.data_start.get_mut();
 
@@ -583,12 +1075,13 @@ impl<W: Write + io::Seek> ZipWriter<W> {
             writer.write_u16::<LittleEndian>(extra_field_length)?;
             writer.seek(io::SeekFrom::Start(header_end))?;
 
-            self.inner.switch_to(file.compression_method)?;
+            let method = file.compression_method;
+            self.start_data(method)?;
         }
 
         self.writing_to_extra_field = false;
         self.writing_to_central_extra_field_only = false;
-        Ok(*data_start)
+        Ok(self.files.last().unwrap().data_start.load())
     }
 
     /// Add a new file using the already compressed data from a ZIP file being read and renames it, this
@@ -617,10 +1110,14 @@ impl<W: Write + io::Seek> ZipWriter<W> {
     ///     Ok(())
     /// }
     /// ```
-    pub fn raw_copy_file_rename<S>(&mut self, mut file: ZipFile, name: S) -> ZipResult<()>
+    pub fn raw_copy_file_rename<S>(&mut self, file: ZipFile, name: S) -> ZipResult<()>
     where
         S: Into<String>,
     {
+        self.raw_copy_named_file(file, EntryName::new(name.into(), false))
+    }
+
+    fn raw_copy_named_file(&mut self, mut file: ZipFile, name: EntryName) -> ZipResult<()> {
         let mut options = FileOptions::default()
             .last_modified_time(file.last_modified())
             .compression_method(file.compression());
@@ -632,9 +1129,10 @@ impl<W: Write + io::Seek> ZipWriter<W> {
             crc32: file.crc32(),
             compressed_size: file.compressed_size(),
             uncompressed_size: file.size(),
+            method_flags: file.flags() & METHOD_FLAGS,
         };
 
-        self.start_entry(name, options, Some(raw_values))?;
+        self.start_named_entry(name, options, Some(raw_values), &[], &[])?;
         self.writing_to_file = true;
         self.writing_raw = true;
 
@@ -645,7 +1143,8 @@ impl<W: Write + io::Seek> ZipWriter<W> {
 
     /// Add a new file using the already compressed data from a ZIP file being read, this allows faster
     /// copies of the `ZipFile` since there is no need to decompress and compress it again. Any `ZipFile`
-    /// metadata is copied and not checked, for example the file CRC.
+    /// metadata is copied and not checked, for example the file CRC. The name is written with the
+    /// same bytes as in the source archive, whatever their encoding.
     ///
     /// ```no_run
     /// use std::fs::File;
@@ -667,8 +1166,13 @@ impl<W: Write + io::Seek> ZipWriter<W> {
     /// }
     /// ```
     pub fn raw_copy_file(&mut self, file: ZipFile) -> ZipResult<()> {
-        let name = file.name().to_owned();
-        self.raw_copy_file_rename(file, name)
+        // Keep the name as it was written, whatever its encoding
+        let name = EntryName {
+            name: file.name().to_owned(),
+            raw: file.name_raw().to_vec(),
+            utf8: file.flags() & (1 << 11) != 0,
+        };
+        self.raw_copy_named_file(file, name)
     }
 
     /// Add a directory entry.
@@ -728,11 +1232,11 @@ impl<W: Write + io::Seek> ZipWriter<W> {
         {
             let writer = self.inner.get_plain();
 
-            let central_start = writer.seek(io::SeekFrom::Current(0))?;
+            let central_start = writer.stream_position()?;
             for file in self.files.iter() {
                 write_central_directory_header(writer, file)?;
             }
-            let central_size = writer.seek(io::SeekFrom::Current(0))? - central_start;
+            let central_size = writer.stream_position()? - central_start;
 
             if self.files.len() > 0xFFFF || central_size > 0xFFFFFFFF || central_start > 0xFFFFFFFF
             {
@@ -799,7 +1303,12 @@ impl<W: Write + io::Seek> Drop for ZipWriter<W> {
 }
 
 impl<W: Write + io::Seek> GenericZipWriter<W> {
-    fn switch_to(&mut self, compression: CompressionMethod) -> ZipResult<()> {
+    fn switch_to(
+        &mut self,
+        compression: CompressionMethod,
+        codec: Option<&Arc<dyn Codec>>,
+        #[cfg(feature = "zstd")] zstd_encoder: &mut Option<ZstdEncoder<'static>>,
+    ) -> ZipResult<()> {
         match self.current_compression() {
             Some(method) if method == compression => return Ok(()),
             None => {
@@ -822,8 +1331,24 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
             GenericZipWriter::Deflater(w) => w.finish()?,
             #[cfg(feature = "bzip2")]
             GenericZipWriter::Bzip2(w) => w.finish()?,
+            #[cfg(feature = "lzma")]
+            GenericZipWriter::Lzma(w) => w.finish()?,
             #[cfg(feature = "zstd")]
-            GenericZipWriter::Zstd(w) => w.finish()?,
+            GenericZipWriter::Zstd(mut w) => {
+                w.finish()?;
+                let (w, mut encoder) = w.into_inner();
+                encoder.reinit()?;
+                // Keep the encoder for the next file, unless the dictionary was replaced
+                if zstd_encoder.is_none() {
+                    *zstd_encoder = Some(encoder);
+                }
+                w
+            }
+            #[cfg(feature = "xz")]
+            GenericZipWriter::Xz(w) => w.finish()?,
+            #[cfg(feature = "ppmd")]
+            GenericZipWriter::Ppmd(w) => w.finish()?,
+            GenericZipWriter::Custom(w) => w.finish()?,
             GenericZipWriter::Closed => {
                 return Err(io::Error::new(
                     io::ErrorKind::BrokenPipe,
@@ -833,6 +1358,11 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
             }
         };
 
+        if let Some(codec) = codec {
+            *self = GenericZipWriter::Custom(CodecWriter::new(bare, &**codec, compression)?);
+            return Ok(());
+        }
+
         *self = {
             #[allow(deprecated)]
             match compression {
@@ -850,6 +1380,26 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
                 CompressionMethod::Bzip2 => {
                     GenericZipWriter::Bzip2(BzEncoder::new(bare, bzip2::Compression::default()))
                 }
+                #[cfg(feature = "lzma")]
+                CompressionMethod::Lzma => GenericZipWriter::Lzma(LzmaWriter::new(bare)?),
+                #[cfg(feature = "legacy")]
+                CompressionMethod::Shrink
+                | CompressionMethod::Reduce1
+                | CompressionMethod::Reduce2
+                | CompressionMethod::Reduce3
+                | CompressionMethod::Reduce4
+                | CompressionMethod::Implode
+                | CompressionMethod::PkwareImplode => {
+                    return Err(ZipError::UnsupportedArchive(
+                        "Legacy compression methods are not supported for writing",
+                    ))
+                }
+                #[cfg(feature = "deflate64")]
+                CompressionMethod::Deflate64 => {
+                    return Err(ZipError::UnsupportedArchive(
+                        "Compressing Deflate64 is not supported",
+                    ))
+                }
                 CompressionMethod::AES => {
                     return Err(ZipError::UnsupportedArchive(
                         "AES compression is not supported for writing",
@@ -857,8 +1407,16 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
                 }
                 #[cfg(feature = "zstd")]
                 CompressionMethod::Zstd => {
-                    GenericZipWriter::Zstd(ZstdEncoder::new(bare, 0).unwrap())
+                    let encoder = match zstd_encoder.take() {
+                        Some(encoder) => encoder,
+                        None => ZstdEncoder::new(0)?,
+                    };
+                    GenericZipWriter::Zstd(ZstdWriter::new(bare, encoder))
                 }
+                #[cfg(feature = "xz")]
+                CompressionMethod::Xz => GenericZipWriter::Xz(XzEncoder::new(bare, 6)),
+                #[cfg(feature = "ppmd")]
+                CompressionMethod::Ppmd => GenericZipWriter::Ppmd(PpmdWriter::new(bare)?),
                 CompressionMethod::Unsupported(..) => {
                     return Err(ZipError::UnsupportedArchive("Unsupported compression"))
                 }
@@ -879,8 +1437,15 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
             GenericZipWriter::Deflater(ref mut w) => Some(w as &mut dyn Write),
             #[cfg(feature = "bzip2")]
             GenericZipWriter::Bzip2(ref mut w) => Some(w as &mut dyn Write),
+            #[cfg(feature = "lzma")]
+            GenericZipWriter::Lzma(ref mut w) => Some(w as &mut dyn Write),
             #[cfg(feature = "zstd")]
             GenericZipWriter::Zstd(ref mut w) => Some(w as &mut dyn Write),
+            #[cfg(feature = "xz")]
+            GenericZipWriter::Xz(ref mut w) => Some(w as &mut dyn Write),
+            #[cfg(feature = "ppmd")]
+            GenericZipWriter::Ppmd(ref mut w) => Some(w as &mut dyn Write),
+            GenericZipWriter::Custom(ref mut w) => Some(w as &mut dyn Write),
             GenericZipWriter::Closed => None,
         }
     }
@@ -907,8 +1472,15 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
             GenericZipWriter::Deflater(..) => Some(CompressionMethod::Deflated),
             #[cfg(feature = "bzip2")]
             GenericZipWriter::Bzip2(..) => Some(CompressionMethod::Bzip2),
+            #[cfg(feature = "lzma")]
+            GenericZipWriter::Lzma(..) => Some(CompressionMethod::Lzma),
             #[cfg(feature = "zstd")]
             GenericZipWriter::Zstd(..) => Some(CompressionMethod::Zstd),
+            #[cfg(feature = "xz")]
+            GenericZipWriter::Xz(..) => Some(CompressionMethod::Xz),
+            #[cfg(feature = "ppmd")]
+            GenericZipWriter::Ppmd(..) => Some(CompressionMethod::Ppmd),
+            GenericZipWriter::Custom(ref w) => Some(w.method()),
             GenericZipWriter::Closed => None,
         }
     }
@@ -921,18 +1493,18 @@ impl<W: Write + io::Seek> GenericZipWriter<W> {
     }
 }
 
+fn general_purpose_flag(file: &ZipFileData) -> u16 {
+    // The name is only flagged as UTF-8 if it was written as UTF-8
+    file.flags & (1u16 << 11 | METHOD_FLAGS)
+}
+
 fn write_local_file_header<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipResult<()> {
     // local file header signature
     writer.write_u32::<LittleEndian>(spec::LOCAL_FILE_HEADER_SIGNATURE)?;
     // version needed to extract
     writer.write_u16::<LittleEndian>(file.version_needed())?;
     // general purpose bit flag
-    let flag = if !file.file_name.is_ascii() {
-        1u16 << 11
-    } else {
-        0
-    };
-    writer.write_u16::<LittleEndian>(flag)?;
+    writer.write_u16::<LittleEndian>(general_purpose_flag(file))?;
     // Compression method
     #[allow(deprecated)]
     writer.write_u16::<LittleEndian>(file.compression_method.to_u16())?;
@@ -954,16 +1526,18 @@ fn write_local_file_header<T: Write>(writer: &mut T, file: &ZipFileData) -> ZipR
         file.uncompressed_size as u32
     })?;
     // file name length
-    writer.write_u16::<LittleEndian>(file.file_name.as_bytes().len() as u16)?;
+    writer.write_u16::<LittleEndian>(file.file_name_raw.len() as u16)?;
     // extra field length
     let extra_field_length = if file.large_file { 20 } else { 0 } + file.extra_field.len() as u16;
     writer.write_u16::<LittleEndian>(extra_field_length)?;
     // file name
-    writer.write_all(file.file_name.as_bytes())?;
+    writer.write_all(&file.file_name_raw)?;
     // zip64 extra field
     if file.large_file {
         write_local_zip64_extra_field(writer, file)?;
     }
+    // extra field
+    writer.write_all(&file.extra_field)?;
 
     Ok(())
 }
@@ -1014,12 +1588,7 @@ fn write_central_directory_header<T: Write>(writer: &mut T, file: &ZipFileData)
     // version needed to extract
     writer.write_u16::<LittleEndian>(file.version_needed())?;
     // general puprose bit flag
-    let flag = if !file.file_name.is_ascii() {
-        1u16 << 11
-    } else {
-        0
-    };
-    writer.write_u16::<LittleEndian>(flag)?;
+    writer.write_u16::<LittleEndian>(general_purpose_flag(file))?;
     // compression method
     #[allow(deprecated)]
     writer.write_u16::<LittleEndian>(file.compression_method.to_u16())?;
@@ -1041,7 +1610,7 @@ fn write_central_directory_header<T: Write>(writer: &mut T, file: &ZipFileData)
         file.uncompressed_size as u32
     })?;
     // file name length
-    writer.write_u16::<LittleEndian>(file.file_name.as_bytes().len() as u16)?;
+    writer.write_u16::<LittleEndian>(file.file_name_raw.len() as u16)?;
     // extra field length
     writer.write_u16::<LittleEndian>(zip64_extra_field_length + file.extra_field.len() as u16)?;
     // file comment length
@@ -1059,7 +1628,7 @@ fn write_central_directory_header<T: Write>(writer: &mut T, file: &ZipFileData)
         file.header_start as u32
     })?;
     // file name
-    writer.write_all(file.file_name.as_bytes())?;
+    writer.write_all(&file.file_name_raw)?;
     // zip64 extra field
     writer.write_all(&zip64_extra_field[..zip64_extra_field_length as usize])?;
     // extra field
@@ -1070,9 +1639,40 @@ fn write_central_directory_header<T: Write>(writer: &mut T, file: &ZipFileData)
     Ok(())
 }
 
-fn validate_extra_data(file: &ZipFileData) -> ZipResult<()> {
-    let mut data = file.extra_field.as_slice();
+/// Encode the extra fields of a header, leaving room for the ZIP64 extended information.
+fn encode_extra_fields(fields: &[ExtraField], zip64_size: usize) -> ZipResult<Vec<u8>> {
+    let mut data = Vec::new();
+    for field in fields {
+        let record_start = data.len();
+        match field {
+            ExtraField::Zip64(_) => {
+                return Err(ZipError::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "No custom ZIP64 extra data allowed",
+                )));
+            }
+            ExtraField::Aes { .. } => {
+                return Err(ZipError::Io(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Writing files with AES encryption is not supported",
+                )));
+            }
+            _ => field.write_to(&mut data)?,
+        }
+        if let ExtraField::Unknown(..) = field {
+            validate_extra_data(&data[record_start..])?;
+        }
+    }
+    if zip64_size + data.len() > 0xFFFF {
+        return Err(ZipError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Extra data exceeds extra field",
+        )));
+    }
+    Ok(data)
+}
 
+fn validate_extra_data(mut data: &[u8]) -> ZipResult<()> {
     if data.len() > 0xFFFF {
         return Err(ZipError::Io(io::Error::new(
             io::ErrorKind::InvalidData,
@@ -1141,7 +1741,7 @@ fn update_local_zip64_extra_field<T: Write + io::Seek>(
     writer: &mut T,
     file: &ZipFileData,
 ) -> ZipResult<()> {
-    let zip64_extra_field = file.header_start + 30 + file.file_name.as_bytes().len() as u64;
+    let zip64_extra_field = file.header_start + 30 + file.file_name_raw.len() as u64;
     writer.seek(io::SeekFrom::Start(zip64_extra_field + 4))?;
     writer.write_u64::<LittleEndian>(file.uncompressed_size)?;
     writer.write_u64::<LittleEndian>(file.compressed_size)?;
@@ -1257,6 +1857,8 @@ mod test {
             last_modified_time: DateTime::default(),
             permissions: Some(33188),
             large_file: false,
+            store_if_incompressible: None,
+            cp437_name: false,
         };
         writer.start_file("mimetype", options).unwrap();
         writer
@@ -1270,6 +1872,116 @@ mod test {
         assert_eq!(result.get_ref(), &v);
     }
 
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    #[test]
+    fn store_if_incompressible() {
+        use crate::read::ZipArchive;
+        use std::io::Read;
+
+        let mut incompressible = vec![0; 4096];
+        getrandom::getrandom(&mut incompressible).unwrap();
+        let compressible = vec![b'z'; 4096];
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .store_if_incompressible(Some(1 << 20));
+        writer.start_file("random.bin", options).unwrap();
+        writer.write_all(&incompressible).unwrap();
+        writer.start_file("zeds.txt", options).unwrap();
+        writer.write_all(&compressible).unwrap();
+        writer.start_file("random_last.bin", options).unwrap();
+        writer.write_all(&incompressible).unwrap();
+        let result = writer.finish().unwrap();
+
+        let mut archive = ZipArchive::new(result).unwrap();
+        for (name, method, expected) in [
+            ("random.bin", CompressionMethod::Stored, &incompressible),
+            ("zeds.txt", CompressionMethod::Deflated, &compressible),
+            (
+                "random_last.bin",
+                CompressionMethod::Stored,
+                &incompressible,
+            ),
+        ] {
+            let mut file = archive.by_name(name).unwrap();
+            assert_eq!(file.compression(), method);
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).unwrap();
+            assert_eq!(&contents, expected);
+        }
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn store_if_incompressible_last_entry() {
+        use crate::read::ZipArchive;
+        use std::io::Read;
+
+        let mut incompressible = vec![0; 1 << 16];
+        getrandom::getrandom(&mut incompressible).unwrap();
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Bzip2)
+            .store_if_incompressible(Some(1 << 20));
+        writer.start_file("random.bin", options).unwrap();
+        writer.write_all(&incompressible).unwrap();
+        let result = writer.finish().unwrap();
+        // The local header, the data, the central header and the footer, without stale data
+        assert_eq!(result.get_ref().len(), 40 + (1 << 16) + 56 + 22);
+
+        let mut archive = ZipArchive::new(result).unwrap();
+        let mut file = archive.by_name("random.bin").unwrap();
+        assert_eq!(file.compression(), CompressionMethod::Stored);
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, incompressible);
+    }
+
+    #[cfg(any(
+        feature = "deflate",
+        feature = "deflate-miniz",
+        feature = "deflate-zlib"
+    ))]
+    #[test]
+    fn store_if_incompressible_too_large() {
+        use crate::read::ZipArchive;
+        use std::io::Read;
+
+        let mut incompressible = vec![0; 4096];
+        getrandom::getrandom(&mut incompressible).unwrap();
+
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer
+            .start_file("exact.bin", options.store_if_incompressible(Some(4096)))
+            .unwrap();
+        writer.write_all(&incompressible).unwrap();
+        writer
+            .start_file("large.bin", options.store_if_incompressible(Some(4095)))
+            .unwrap();
+        writer.write_all(&incompressible[..1000]).unwrap();
+        writer.write_all(&incompressible[1000..]).unwrap();
+        let result = writer.finish().unwrap();
+
+        let mut archive = ZipArchive::new(result).unwrap();
+        for (name, method) in [
+            ("exact.bin", CompressionMethod::Stored),
+            ("large.bin", CompressionMethod::Deflated),
+        ] {
+            let mut file = archive.by_name(name).unwrap();
+            assert_eq!(file.compression(), method);
+            let mut contents = Vec::new();
+            file.read_to_end(&mut contents).unwrap();
+            assert_eq!(contents, incompressible);
+        }
+    }
+
     #[test]
     fn path_to_string() {
         let mut path = std::path::PathBuf::new();
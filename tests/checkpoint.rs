@@ -0,0 +1,170 @@
+mod common;
+
+use std::io::Cursor;
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive};
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib",
+    feature = "zstd"
+))]
+use {
+    std::io::{Read, Seek, SeekFrom},
+    zip::read::CheckpointIndex,
+};
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib",
+    feature = "zstd"
+))]
+fn sample() -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut state = 7u32;
+    while data.len() < 1 << 20 {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        let line = format!("{:08} {:x}\n", data.len(), state >> 8);
+        data.extend_from_slice(line.as_bytes());
+    }
+    data
+}
+
+/// Open an archive with `data` as its only file.
+fn open_archive(method: CompressionMethod, data: &[u8]) -> ZipArchive<Cursor<Vec<u8>>> {
+    let options = FileOptions::default().compression_method(method);
+    let archive = common::make_archive_with(options, &[("data.txt", data)]);
+    ZipArchive::new(Cursor::new(archive)).unwrap()
+}
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib",
+    feature = "zstd"
+))]
+fn check_random_access<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    index: &CheckpointIndex,
+    data: &[u8],
+) {
+    let mut file = archive.by_index_with_checkpoints(0, index).unwrap();
+    assert_eq!(file.size(), data.len() as u64);
+
+    let mut buf = [0; 100];
+    for &offset in &[900_000, 10, 500_000, 500_050, 0, data.len() as u64 - 100] {
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf[..], &data[offset as usize..offset as usize + 100]);
+    }
+
+    file.seek(SeekFrom::End(-50)).unwrap();
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).unwrap();
+    assert_eq!(tail, &data[data.len() - 50..]);
+    assert_eq!(file.read(&mut buf).unwrap(), 0);
+}
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+#[test]
+fn deflate() {
+    let data = sample();
+    let mut archive = open_archive(CompressionMethod::Deflated, &data);
+    let index = archive.build_checkpoint_index(0, 64 * 1024).unwrap();
+    assert!(index.len() > 2);
+    check_random_access(&mut archive, &index, &data);
+
+    // The index can be saved and loaded again
+    let mut saved = Vec::new();
+    index.write(&mut saved).unwrap();
+    let index = CheckpointIndex::parse(&mut saved.as_slice()).unwrap();
+    check_random_access(&mut archive, &index, &data);
+
+    // An index for another file is rejected
+    let mut other = open_archive(CompressionMethod::Deflated, &data[1..]);
+    match other.by_index_with_checkpoints(0, &index) {
+        Err(ZipError::InvalidArchive(_)) => {}
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("used an index for another file"),
+    }
+}
+
+/// Compresses every 64 KiB of data as a separate zstd frame.
+#[cfg(feature = "zstd")]
+struct ZstdFrames;
+
+#[cfg(feature = "zstd")]
+impl zip::codec::Codec for ZstdFrames {
+    fn encoder(&self) -> std::io::Result<Box<dyn zip::codec::Encoder>> {
+        Ok(Box::new(ZstdFramesEncoder(Vec::new())))
+    }
+}
+
+#[cfg(feature = "zstd")]
+struct ZstdFramesEncoder(Vec<u8>);
+
+#[cfg(feature = "zstd")]
+impl zip::codec::Encoder for ZstdFramesEncoder {
+    fn write(&mut self, input: &[u8], output: &mut dyn std::io::Write) -> std::io::Result<()> {
+        self.0.extend_from_slice(input);
+        while self.0.len() >= 1 << 16 {
+            let frame: Vec<u8> = self.0.drain(..1 << 16).collect();
+            output.write_all(&zstd::encode_all(frame.as_slice(), 3)?)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, output: &mut dyn std::io::Write) -> std::io::Result<()> {
+        if !self.0.is_empty() {
+            output.write_all(&zstd::encode_all(self.0.as_slice(), 3)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_frames() {
+    use std::io::Write;
+    use zip::ZipWriter;
+
+    let data = sample();
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.register_codec(93, ZstdFrames);
+    let options = FileOptions::default().compression_method(CompressionMethod::Zstd);
+    zip.start_file("data.txt", options).unwrap();
+    zip.write_all(&data).unwrap();
+    let mut archive = ZipArchive::new(zip.finish().unwrap()).unwrap();
+
+    let index = archive.build_checkpoint_index(0, 200 * 1024).unwrap();
+    // One checkpoint at the start and one every four frames
+    assert_eq!(index.len(), 1 + (data.len() - 1) / (256 * 1024));
+    check_random_access(&mut archive, &index, &data);
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_single_frame() {
+    let data = sample();
+    let mut archive = open_archive(CompressionMethod::Zstd, &data);
+    let index = archive.build_checkpoint_index(0, 64 * 1024).unwrap();
+    assert_eq!(index.len(), 1);
+    check_random_access(&mut archive, &index, &data);
+}
+
+#[test]
+fn unsupported() {
+    let mut archive = open_archive(CompressionMethod::Stored, b"data");
+    match archive.build_checkpoint_index(0, 1024) {
+        Err(ZipError::UnsupportedArchive(_)) => {}
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("built an index for a stored file"),
+    }
+}
@@ -0,0 +1,177 @@
+use std::io::{self, BufRead, Cursor, Read, Write};
+use zip::codec::{Codec, Decoder, Encoder};
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const RUN_LENGTH: u16 = 0xff01;
+
+/// Codes the data as pairs of a count and a byte.
+struct RunLength;
+
+impl Codec for RunLength {
+    fn decoder(&self, _uncompressed_size: u64) -> io::Result<Box<dyn Decoder>> {
+        Ok(Box::new(RunLengthDecoder { byte: 0, count: 0 }))
+    }
+
+    fn encoder(&self) -> io::Result<Box<dyn Encoder>> {
+        Ok(Box::new(RunLengthEncoder { run: None }))
+    }
+}
+
+struct RunLengthDecoder {
+    byte: u8,
+    count: u8,
+}
+
+impl Decoder for RunLengthDecoder {
+    fn read(&mut self, input: &mut dyn BufRead, buf: &mut [u8]) -> io::Result<usize> {
+        if self.count == 0 {
+            let mut pair = [0; 2];
+            match input.read_exact(&mut pair) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(0),
+                Err(e) => return Err(e),
+            }
+            self.count = pair[0];
+            self.byte = pair[1];
+        }
+        let count = buf.len().min(self.count as usize);
+        buf[..count].iter_mut().for_each(|byte| *byte = self.byte);
+        self.count -= count as u8;
+        Ok(count)
+    }
+}
+
+struct RunLengthEncoder {
+    run: Option<(u8, u8)>,
+}
+
+impl Encoder for RunLengthEncoder {
+    fn write(&mut self, input: &[u8], output: &mut dyn Write) -> io::Result<()> {
+        for &byte in input {
+            match &mut self.run {
+                Some((count, run_byte)) if *run_byte == byte && *count < u8::MAX => *count += 1,
+                run => {
+                    if let Some((count, run_byte)) = run.replace((1, byte)) {
+                        output.write_all(&[count, run_byte])?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, output: &mut dyn Write) -> io::Result<()> {
+        if let Some((count, byte)) = self.run.take() {
+            output.write_all(&[count, byte])?;
+        }
+        Ok(())
+    }
+}
+
+/// Leaves the data as it is.
+struct Identity;
+
+impl Codec for Identity {
+    fn decoder(&self, _uncompressed_size: u64) -> io::Result<Box<dyn Decoder>> {
+        Ok(Box::new(Identity))
+    }
+
+    fn encoder(&self) -> io::Result<Box<dyn Encoder>> {
+        Ok(Box::new(Identity))
+    }
+}
+
+impl Decoder for Identity {
+    fn read(&mut self, input: &mut dyn BufRead, buf: &mut [u8]) -> io::Result<usize> {
+        input.read(buf)
+    }
+}
+
+impl Encoder for Identity {
+    fn write(&mut self, input: &[u8], output: &mut dyn Write) -> io::Result<()> {
+        output.write_all(input)
+    }
+
+    fn finish(&mut self, _output: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Can only decompress.
+struct DecodeOnly;
+
+impl Codec for DecodeOnly {
+    fn decoder(&self, _uncompressed_size: u64) -> io::Result<Box<dyn Decoder>> {
+        Ok(Box::new(Identity))
+    }
+}
+
+fn sample() -> Vec<u8> {
+    let mut data = Vec::new();
+    for i in 0..100 {
+        data.extend_from_slice(&vec![b'a' + (i % 26) as u8; i * 7 % 400]);
+    }
+    data
+}
+
+#[test]
+fn custom_method() {
+    let data = sample();
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.register_codec(RUN_LENGTH, RunLength);
+    let options =
+        FileOptions::default().compression_method(CompressionMethod::from_u16(RUN_LENGTH));
+    zip.start_file("runs.txt", options).unwrap();
+    zip.write_all(&data).unwrap();
+    let archive = zip.finish().unwrap();
+
+    let mut archive = ZipArchive::new(archive).unwrap();
+    match archive.by_name("runs.txt") {
+        Err(ZipError::UnsupportedArchive(_)) => {}
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("read a file without its codec"),
+    }
+
+    archive.register_codec(RUN_LENGTH, RunLength);
+    let mut file = archive.by_name("runs.txt").unwrap();
+    assert!(file.compressed_size() < data.len() as u64 / 10);
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, data);
+}
+
+#[test]
+fn replace_builtin_method() {
+    let data = sample();
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.register_codec(8, Identity);
+    let options = FileOptions::default().compression_method(CompressionMethod::DEFLATE);
+    zip.start_file("identity.txt", options).unwrap();
+    zip.write_all(&data).unwrap();
+    let archive = zip.finish().unwrap();
+
+    let mut archive = ZipArchive::new(archive).unwrap();
+    archive.register_codec(8, Identity);
+    let mut file = archive.by_name("identity.txt").unwrap();
+    assert_eq!(file.compression(), CompressionMethod::DEFLATE);
+    assert_eq!(file.compressed_size(), data.len() as u64);
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, data);
+}
+
+#[test]
+fn decode_only_codec() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.register_codec(RUN_LENGTH, DecodeOnly);
+    let options =
+        FileOptions::default().compression_method(CompressionMethod::from_u16(RUN_LENGTH));
+    match zip.start_file("runs.txt", options) {
+        Err(ZipError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::Unsupported),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
@@ -0,0 +1,82 @@
+//! Helpers shared by the integration tests
+//!
+//! Every test file only uses some of them.
+#![allow(dead_code)]
+
+use std::io::{Cursor, Read, Seek, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Write an archive with the given files, using `options` for all of them.
+pub fn make_archive_with(options: FileOptions, files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for &(name, content) in files {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+/// Write an archive with the given files, stored without compression.
+pub fn make_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+    make_archive_with(options, files)
+}
+
+/// Returns the offset of the first occurrence of `needle` in `data`.
+pub fn find(data: &[u8], needle: &[u8]) -> usize {
+    find_nth(data, needle, 0)
+}
+
+/// Returns the offset of the last occurrence of `needle` in `data`.
+pub fn rfind(data: &[u8], needle: &[u8]) -> usize {
+    data.windows(needle.len())
+        .rposition(|window| window == needle)
+        .unwrap()
+}
+
+/// Returns the offset of the nth occurrence of `needle` in `data`.
+pub fn find_nth(data: &[u8], needle: &[u8], n: usize) -> usize {
+    data.windows(needle.len())
+        .enumerate()
+        .filter(|(_, window)| *window == needle)
+        .nth(n)
+        .unwrap()
+        .0
+}
+
+/// Returns the offset of the nth central directory header.
+pub fn central_header(data: &[u8], n: usize) -> usize {
+    find_nth(data, b"PK\x01\x02", n)
+}
+
+/// Read the contents of a file.
+pub fn read<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Vec<u8> {
+    let mut content = Vec::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_end(&mut content)
+        .unwrap();
+    content
+}
+
+/// Read the names and contents of all files.
+pub fn read_all<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Vec<(String, Vec<u8>)> {
+    (0..archive.len())
+        .map(|i| {
+            let mut file = archive.by_index(i).unwrap();
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).unwrap();
+            (file.name().to_owned(), content)
+        })
+        .collect()
+}
+
+/// The names and contents of files as [`read_all`] returns them.
+pub fn owned_files(files: &[(&str, &[u8])]) -> Vec<(String, Vec<u8>)> {
+    files
+        .iter()
+        .map(|&(name, content)| (name.to_owned(), content.to_vec()))
+        .collect()
+}
@@ -0,0 +1,25 @@
+#![cfg(feature = "deflate64")]
+
+use std::io::{self, Read};
+use zip::{CompressionMethod, ZipArchive};
+
+#[test]
+fn decompress_deflate64() {
+    let mut v = Vec::new();
+    v.extend_from_slice(include_bytes!("data/deflate64.zip"));
+    let mut archive = ZipArchive::new(io::Cursor::new(v)).expect("couldn't open test zip file");
+
+    let mut file = archive
+        .by_name("data.txt")
+        .expect("couldn't find file in archive");
+    assert_eq!("data.txt", file.name());
+    assert_eq!(CompressionMethod::Deflate64, file.compression());
+
+    let mut content = Vec::new();
+    file.read_to_end(&mut content).expect("couldn't read file");
+    // The entry uses a match longer than 258 bytes and a match further back than 32 KiB
+    let mut expected = b"abc".repeat(13334);
+    expected.truncate(40000);
+    expected.extend_from_slice(b"Zabcab");
+    assert_eq!(content, expected);
+}
@@ -0,0 +1,180 @@
+use std::io::{Cursor, Write};
+use zip::extra_fields::{AesMode, AesVendorVersion, ExtraField};
+use zip::read::ReadOptions;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+fn open(path: &str) -> ZipArchive<Cursor<Vec<u8>>> {
+    let options = ReadOptions::default().local_extra_fields(true);
+    ZipArchive::with_options(Cursor::new(std::fs::read(path).unwrap()), options).unwrap()
+}
+
+#[test]
+fn info_zip_fields() {
+    let mut archive = open("tests/data/files_and_dirs.zip");
+    let file = archive.by_index_raw(0).unwrap();
+    let owner = ExtraField::UnixOwner { uid: 501, gid: 20 };
+
+    // The central header only has the modification time
+    let central: Vec<_> = file.extra_fields().map(Result::unwrap).collect();
+    assert_eq!(
+        central,
+        vec![
+            ExtraField::ExtendedTimestamp {
+                modified: Some(1548598024),
+                accessed: None,
+                created: None,
+            },
+            owner.clone(),
+        ]
+    );
+    let local: Vec<_> = file.local_extra_fields().map(Result::unwrap).collect();
+    assert_eq!(
+        local,
+        vec![
+            ExtraField::ExtendedTimestamp {
+                modified: Some(1548598024),
+                accessed: Some(1548937988),
+                created: None,
+            },
+            owner,
+        ]
+    );
+}
+
+#[test]
+fn ntfs_and_aes_fields() {
+    let mut archive = open("tests/data/aes_archive.zip");
+    let file = archive.by_index_raw(0).unwrap();
+    let fields: Vec<_> = file.extra_fields().map(Result::unwrap).collect();
+    assert_eq!(
+        fields,
+        vec![
+            ExtraField::Ntfs {
+                modified: 132876182110000000,
+                accessed: 132876181910000000,
+                created: 132876182110000000,
+            },
+            ExtraField::Aes {
+                vendor_version: AesVendorVersion::Ae2,
+                mode: AesMode::Aes128,
+                compression_method: CompressionMethod::Stored,
+            },
+        ]
+    );
+}
+
+#[test]
+fn zip64_and_padding_fields() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+    zip.start_file_aligned("aligned", options, 64).unwrap();
+    zip.write_all(b"content").unwrap();
+    let data = zip.finish().unwrap().into_inner();
+
+    let mut archive = ZipArchive::new(Cursor::new(data.as_slice())).unwrap();
+    // The local header is only read for its extra field when asked to
+    assert!(archive.by_index(0).unwrap().local_extra_data().is_empty());
+    let options = ReadOptions::default().local_extra_fields(true);
+    let mut archive = ZipArchive::with_options(Cursor::new(data.as_slice()), options).unwrap();
+    let file = archive.by_index(0).unwrap();
+    let local: Vec<_> = file.local_extra_fields().map(Result::unwrap).collect();
+    assert_eq!(local.len(), 2);
+    assert_eq!(local[0], ExtraField::Zip64(vec![7, 7]));
+    assert_eq!(local[1].id(), 0x617a);
+    assert_eq!(file.data_start() % 64, 0);
+
+    // Reading from a stream only has the local header
+    let mut stream = data.as_slice();
+    let file = zip::read::read_zipfile_from_stream(&mut stream)
+        .unwrap()
+        .unwrap();
+    assert_eq!(file.extra_fields().count(), 2);
+    assert_eq!(file.local_extra_data(), file.extra_data());
+}
+
+#[test]
+fn write_fields() {
+    let local = [
+        ExtraField::ExtendedTimestamp {
+            modified: Some(1),
+            accessed: Some(2),
+            created: None,
+        },
+        ExtraField::UnixOwner {
+            uid: 1000,
+            gid: 100,
+        },
+    ];
+    let central = [
+        ExtraField::ExtendedTimestamp {
+            modified: Some(1),
+            accessed: None,
+            created: None,
+        },
+        ExtraField::Unknown(0xcafe, b"raw"),
+    ];
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+    zip.start_file_with_extra_fields("file", options, &local, &central)
+        .unwrap();
+    zip.write_all(b"content").unwrap();
+    let data = zip.finish().unwrap().into_inner();
+
+    let options = ReadOptions::default().local_extra_fields(true);
+    let mut archive = ZipArchive::with_options(Cursor::new(data), options).unwrap();
+    assert!(archive.verify().is_ok());
+    let file = archive.by_index(0).unwrap();
+    let fields: Vec<_> = file.local_extra_fields().map(Result::unwrap).collect();
+    // The writer adds the ZIP64 extended information itself
+    assert_eq!(fields[0], ExtraField::Zip64(vec![7, 7]));
+    assert_eq!(fields[1..], local);
+    let fields: Vec<_> = file.extra_fields().map(Result::unwrap).collect();
+    assert_eq!(fields, central);
+}
+
+#[test]
+fn invalid_write_fields() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default();
+    let zip64 = [ExtraField::Zip64(vec![0, 0])];
+    assert!(zip
+        .start_file_with_extra_fields("zip64", options, &zip64, &zip64)
+        .is_err());
+
+    // The limit includes the ZIP64 extended information of the writer
+    let padding = [ExtraField::Padding(0xFFFF - 4 - 19)];
+    assert!(zip
+        .start_file_with_extra_fields("padding", options, &padding, &[])
+        .is_ok());
+    assert!(zip
+        .start_file_with_extra_fields("large", options.large_file(true), &padding, &[])
+        .is_err());
+}
+
+#[test]
+fn unicode_path() {
+    // A name that isn't UTF-8 with the real name in a Unicode Path field
+    let fields = [ExtraField::UnicodePath {
+        crc32: crc32fast::hash(b"a.txt"),
+        name: "ä.txt",
+    }];
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file_with_extra_fields("a.txt", FileOptions::default(), &fields, &fields)
+        .unwrap();
+    let data = zip.finish().unwrap().into_inner();
+
+    let mut archive = ZipArchive::new(Cursor::new(data.as_slice())).unwrap();
+    assert_eq!(archive.file_names().collect::<Vec<_>>(), ["ä.txt"]);
+    assert_eq!(archive.by_name("ä.txt").unwrap().name_raw(), b"a.txt");
+
+    let mut stream = data.as_slice();
+    let file = zip::read::read_zipfile_from_stream(&mut stream)
+        .unwrap()
+        .unwrap();
+    assert_eq!(file.name(), "ä.txt");
+}
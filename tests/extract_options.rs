@@ -0,0 +1,166 @@
+mod common;
+
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use zip::read::{ExtractOptions, Overwrite};
+use zip::write::FileOptions;
+use zip::{DateTime, ZipArchive};
+
+fn output_directory(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("zip-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    path
+}
+
+fn make_archive() -> ZipArchive<Cursor<Vec<u8>>> {
+    let options = FileOptions::default()
+        .last_modified_time(DateTime::from_date_and_time(2020, 6, 15, 12, 30, 0).unwrap())
+        .unix_permissions(0o4777);
+    let data = common::make_archive_with(
+        options,
+        &[
+            ("a.txt", &b"new a"[..]),
+            ("b.bin", &b"new b"[..]),
+            ("sub/c.txt", &b"new c"[..]),
+        ],
+    );
+    ZipArchive::new(Cursor::new(data)).unwrap()
+}
+
+#[test]
+fn overwrite() {
+    let directory = output_directory("extract-overwrite");
+    let mut archive = make_archive();
+
+    fs::write(directory.join("a.txt"), "old a").unwrap();
+    let options = ExtractOptions::default().overwrite(Overwrite::Skip);
+    archive.extract_with(&directory, options).unwrap();
+    assert_eq!(fs::read(directory.join("a.txt")).unwrap(), b"old a");
+    assert_eq!(fs::read(directory.join("b.bin")).unwrap(), b"new b");
+
+    let options = ExtractOptions::default().overwrite(Overwrite::Error);
+    match archive.extract_with(&directory, options) {
+        Err(zip::result::ZipError::Io(e)) => {
+            assert_eq!(e.kind(), std::io::ErrorKind::AlreadyExists)
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    // The existing file was modified now, after the file in the archive
+    let options = ExtractOptions::default().overwrite(Overwrite::IfNewer);
+    archive.extract_with(&directory, options).unwrap();
+    assert_eq!(fs::read(directory.join("a.txt")).unwrap(), b"old a");
+
+    archive
+        .extract_with(&directory, ExtractOptions::default())
+        .unwrap();
+    assert_eq!(fs::read(directory.join("a.txt")).unwrap(), b"new a");
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn filter() {
+    let directory = output_directory("extract-filter");
+    let mut archive = make_archive();
+
+    let only_text = |path: &Path| path.extension() == Some("txt".as_ref());
+    let options = ExtractOptions::default().filter(&only_text);
+    archive.extract_with(&directory, options).unwrap();
+    assert!(directory.join("a.txt").exists());
+    assert!(!directory.join("b.bin").exists());
+    assert_eq!(fs::read(directory.join("sub/c.txt")).unwrap(), b"new c");
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let directory = output_directory("extract-permissions");
+    let mut archive = make_archive();
+
+    let options = ExtractOptions::default()
+        .umask(0o022)
+        .strip_special_bits(true)
+        .directory_mode(Some(0o700));
+    archive.extract_with(&directory, options).unwrap();
+    let mode = |path: &str| {
+        fs::metadata(directory.join(path))
+            .unwrap()
+            .permissions()
+            .mode()
+    };
+    assert_eq!(mode("a.txt") & 0o7777, 0o755);
+    assert_eq!(mode("sub") & 0o777, 0o700);
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(feature = "filetime")]
+#[test]
+fn restore_modified_time() {
+    let directory = output_directory("extract-modified-time");
+    let mut archive = make_archive();
+
+    let options = ExtractOptions::default().restore_modified_time(true);
+    archive.extract_with(&directory, options).unwrap();
+    let modified = fs::metadata(directory.join("a.txt"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).unwrap();
+    assert_eq!(since_epoch.as_secs(), 1592224200);
+
+    // The file in the archive is newer than the one on disk now
+    fs::write(directory.join("a.txt"), "old a").unwrap();
+    let old = filetime::FileTime::from_unix_time(1500000000, 0);
+    filetime::set_file_mtime(directory.join("a.txt"), old).unwrap();
+    let options = ExtractOptions::default().overwrite(Overwrite::IfNewer);
+    archive.extract_with(&directory, options).unwrap();
+    assert_eq!(fs::read(directory.join("a.txt")).unwrap(), b"new a");
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(feature = "filetime")]
+#[test]
+fn extended_timestamp() {
+    use std::io::Write;
+    use zip::extra_fields::ExtraField;
+    use zip::ZipWriter;
+
+    let directory = output_directory("extract-extended-timestamp");
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    // The MS-DOS time is in local time, while the extended timestamp is in UTC and takes
+    // precedence
+    let options = FileOptions::default()
+        .last_modified_time(DateTime::from_date_and_time(2020, 6, 15, 12, 30, 0).unwrap());
+    let fields = [ExtraField::ExtendedTimestamp {
+        modified: Some(1400000000),
+        accessed: None,
+        created: None,
+    }];
+    zip.start_file_with_extra_fields("a.txt", options, &fields, &fields)
+        .unwrap();
+    zip.write_all(b"new a").unwrap();
+    let mut archive = ZipArchive::new(zip.finish().unwrap()).unwrap();
+
+    let options = ExtractOptions::default().restore_modified_time(true);
+    archive.extract_with(&directory, options).unwrap();
+    let modified = fs::metadata(directory.join("a.txt"))
+        .unwrap()
+        .modified()
+        .unwrap();
+    let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).unwrap();
+    assert_eq!(since_epoch.as_secs(), 1400000000);
+
+    // The file on disk is older than the MS-DOS time, but newer than the extended timestamp
+    fs::write(directory.join("a.txt"), "old a").unwrap();
+    let old = filetime::FileTime::from_unix_time(1500000000, 0);
+    filetime::set_file_mtime(directory.join("a.txt"), old).unwrap();
+    let options = ExtractOptions::default().overwrite(Overwrite::IfNewer);
+    archive.extract_with(&directory, options).unwrap();
+    assert_eq!(fs::read(directory.join("a.txt")).unwrap(), b"old a");
+    fs::remove_dir_all(&directory).unwrap();
+}
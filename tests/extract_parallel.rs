@@ -0,0 +1,92 @@
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zip::read::{ExtractOptions, Overwrite, ReadAtCursor};
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+fn output_directory(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("zip-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+fn contents(i: usize) -> Vec<u8> {
+    format!("file {} ", i).repeat(500 + i * 50).into_bytes()
+}
+
+#[test]
+fn extract_parallel() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.add_directory("empty", FileOptions::default()).unwrap();
+    for i in 0..40 {
+        // Files in a directory that has no entry of its own
+        zip.start_file(format!("dir{}/{}.txt", i % 3, i), FileOptions::default())
+            .unwrap();
+        zip.write_all(&contents(i)).unwrap();
+    }
+    let data: Arc<[u8]> = zip.finish().unwrap().into_inner().into();
+    let archive = Arc::new(ZipArchive::new(ReadAtCursor::new(data)).unwrap());
+
+    let directory = output_directory("extract-parallel");
+    archive
+        .extract_parallel(&directory, ExtractOptions::default(), 4)
+        .unwrap();
+    assert!(directory.join("empty").is_dir());
+    for i in 0..40 {
+        let path = directory.join(format!("dir{}/{}.txt", i % 3, i));
+        assert_eq!(fs::read(path).unwrap(), contents(i));
+    }
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn extract_parallel_invalid_path() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("fine.txt", FileOptions::default()).unwrap();
+    zip.start_file("../escape.txt", FileOptions::default())
+        .unwrap();
+    let data = zip.finish().unwrap().into_inner();
+    let archive = Arc::new(ZipArchive::new(ReadAtCursor::new(data)).unwrap());
+
+    // Paths are checked before anything is written
+    let directory = output_directory("extract-parallel-invalid");
+    match archive.extract_parallel(&directory, ExtractOptions::default(), 2) {
+        Err(ZipError::InvalidArchive(_)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert!(!directory.join("fine.txt").exists());
+    let _ = fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn extract_parallel_with_options() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..10 {
+        zip.start_file(format!("{}.txt", i), FileOptions::default())
+            .unwrap();
+        zip.write_all(&contents(i)).unwrap();
+    }
+    zip.start_file("skipped.bin", FileOptions::default())
+        .unwrap();
+    let data = zip.finish().unwrap().into_inner();
+    let archive = Arc::new(ZipArchive::new(ReadAtCursor::new(data)).unwrap());
+
+    let directory = output_directory("extract-parallel-options");
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("0.txt"), b"existing").unwrap();
+    let only_text = |path: &Path| path.extension() == Some("txt".as_ref());
+    let options = ExtractOptions::default()
+        .overwrite(Overwrite::Skip)
+        .filter(&only_text);
+    archive.extract_parallel(&directory, options, 3).unwrap();
+    assert_eq!(fs::read(directory.join("0.txt")).unwrap(), b"existing");
+    for i in 1..10 {
+        let path = directory.join(format!("{}.txt", i));
+        assert_eq!(fs::read(path).unwrap(), contents(i));
+    }
+    assert!(!directory.join("skipped.bin").exists());
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "legacy")]
+
+use std::io::{self, Read};
+use zip::{CompressionMethod, ZipArchive};
+
+/// Generates the text stored in `legacy.zip`.
+fn text(size: usize) -> Vec<u8> {
+    const WORDS: [&str; 16] = [
+        "the", "quick", "brown", "fox", "jumps", "over", "lazy", "dog", "zip", "archive", "legacy",
+        "method", "shrink", "reduce", "implode", "PKZIP",
+    ];
+    let mut x = 1u32;
+    let mut text = Vec::new();
+    while text.len() < size {
+        x = x.wrapping_mul(1103515245).wrapping_add(12345) & 0x7fff_ffff;
+        text.extend_from_slice(WORDS[(x >> 16) as usize % WORDS.len()].as_bytes());
+        text.push(if (x >> 8) & 7 != 0 { b' ' } else { b'\n' });
+    }
+    text.truncate(size);
+    text
+}
+
+#[test]
+fn decompress_legacy_methods() {
+    let mut v = Vec::new();
+    v.extend_from_slice(include_bytes!("data/legacy.zip"));
+    let mut archive = ZipArchive::new(io::Cursor::new(v)).expect("couldn't open test zip file");
+
+    let files = [
+        ("shrink.txt", CompressionMethod::SHRINK, 70000),
+        ("reduce1.txt", CompressionMethod::REDUCE_1, 20000),
+        ("reduce2.txt", CompressionMethod::REDUCE_2, 20000),
+        ("reduce3.txt", CompressionMethod::REDUCE_3, 20000),
+        ("reduce4.txt", CompressionMethod::REDUCE_4, 20000),
+        // 4 KiB and 8 KiB dictionaries, with and without a literal tree
+        ("implode0.txt", CompressionMethod::IMPLODE, 20000),
+        ("implode2.txt", CompressionMethod::IMPLODE, 20000),
+        ("implode4.txt", CompressionMethod::IMPLODE, 20000),
+        ("implode6.txt", CompressionMethod::IMPLODE, 20000),
+        // Uncoded and coded literals
+        (
+            "pkware_implode0.txt",
+            CompressionMethod::PKWARE_IMPLODE,
+            20000,
+        ),
+        (
+            "pkware_implode1.txt",
+            CompressionMethod::PKWARE_IMPLODE,
+            20000,
+        ),
+    ];
+    for &(name, method, size) in &files {
+        let mut file = archive
+            .by_name(name)
+            .expect("couldn't find file in archive");
+        assert_eq!(method, file.compression());
+
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)
+            .unwrap_or_else(|e| panic!("couldn't read {}: {}", name, e));
+        assert!(content == text(size), "wrong content for {}", name);
+    }
+}
+
+#[test]
+fn raw_copy_keeps_method_flags() {
+    let data = include_bytes!("data/legacy.zip");
+    let mut output = io::Cursor::new(Vec::new());
+    let report = ZipArchive::repair(io::Cursor::new(&data[..]), &mut output).unwrap();
+    assert!(report.failures().is_empty());
+
+    let mut original = ZipArchive::new(io::Cursor::new(&data[..])).unwrap();
+    let mut repaired = ZipArchive::new(output).unwrap();
+    assert_eq!(repaired.len(), original.len());
+    for i in 0..original.len() {
+        let mut expected = Vec::new();
+        let mut file = original.by_index(i).unwrap();
+        file.read_to_end(&mut expected).unwrap();
+        let name = file.name().to_owned();
+
+        let mut content = Vec::new();
+        repaired
+            .by_name(&name)
+            .unwrap()
+            .read_to_end(&mut content)
+            .unwrap_or_else(|e| panic!("couldn't read {}: {}", name, e));
+        assert!(content == expected, "wrong content for {}", name);
+    }
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use common::{central_header, make_archive_with};
+use std::io::Cursor;
+use zip::read::{Limits, Overlap, ReadOptions};
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive};
+
+/// Write an archive with files of the given sizes, filled with zeros.
+fn make_archive(method: CompressionMethod, files: &[(&str, usize)]) -> Vec<u8> {
+    let contents: Vec<_> = files
+        .iter()
+        .map(|&(name, size)| (name, vec![0; size]))
+        .collect();
+    let files: Vec<_> = contents
+        .iter()
+        .map(|(name, content)| (*name, content.as_slice()))
+        .collect();
+    make_archive_with(FileOptions::default().compression_method(method), &files)
+}
+
+fn assert_limit_exceeded<T>(result: Result<T, ZipError>) {
+    match result {
+        Err(ZipError::LimitExceeded(_)) => {}
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("limit was not enforced"),
+    }
+}
+
+#[test]
+fn archive_limits() {
+    let files = [("a", 1000), ("b", 1000), ("c", 1000)];
+    let data = make_archive(CompressionMethod::Stored, &files);
+    let open = |limits| {
+        ZipArchive::with_options(
+            Cursor::new(data.as_slice()),
+            ReadOptions::default().limits(limits),
+        )
+    };
+
+    assert!(open(Limits::default().max_files(3)).is_ok());
+    assert_limit_exceeded(open(Limits::default().max_files(2)));
+    assert!(open(Limits::default().max_total_size(3000)).is_ok());
+    assert_limit_exceeded(open(Limits::default().max_total_size(2999)));
+    assert!(open(Limits::default().max_central_directory_size(1000)).is_ok());
+    assert_limit_exceeded(open(Limits::default().max_central_directory_size(100)));
+}
+
+#[test]
+fn scanned_archive_limits() {
+    let data = make_archive(CompressionMethod::Stored, &[("a", 1000), ("b", 1000)]);
+    let recover = |limits| {
+        let options = ReadOptions::default().limits(limits);
+        ZipArchive::recover_with_options(Cursor::new(data.as_slice()), options)
+    };
+
+    assert!(recover(Limits::default().max_files(2)).is_ok());
+    assert_limit_exceeded(recover(Limits::default().max_files(1)));
+    assert_limit_exceeded(recover(Limits::default().max_total_size(1999)));
+
+    let options = ReadOptions::default().limits(Limits::default().max_files(1));
+    let repaired = ZipArchive::repair_with_options(
+        Cursor::new(data.as_slice()),
+        Cursor::new(Vec::new()),
+        options,
+    );
+    assert_limit_exceeded(repaired);
+}
+
+#[test]
+fn file_size() {
+    let data = make_archive(CompressionMethod::Stored, &[("small", 10), ("large", 1000)]);
+    let limits = Limits::default().max_file_size(100);
+    let mut archive =
+        ZipArchive::with_options(Cursor::new(data), ReadOptions::default().limits(limits)).unwrap();
+    assert!(archive.by_name("small").is_ok());
+    assert_limit_exceeded(archive.by_name("large"));
+}
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+#[test]
+fn compression_ratio() {
+    let data = make_archive(CompressionMethod::Deflated, &[("zeros", 1 << 20)]);
+    let limits = Limits::default().max_compression_ratio(100);
+    let mut archive =
+        ZipArchive::with_options(Cursor::new(data), ReadOptions::default().limits(limits)).unwrap();
+    assert_limit_exceeded(archive.by_name("zeros"));
+}
+
+/// Replace the uncompressed size in the local and central headers of the only file.
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+fn declare_size(data: &mut [u8], size: u32) {
+    let local = common::find(data, b"PK\x03\x04") + 22;
+    let central = common::find(data, b"PK\x01\x02") + 24;
+    data[local..local + 4].copy_from_slice(&size.to_le_bytes());
+    data[central..central + 4].copy_from_slice(&size.to_le_bytes());
+}
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+#[test]
+fn understated_size() {
+    use std::io::Read;
+
+    let mut data = make_archive(CompressionMethod::Deflated, &[("zeros", 1 << 20)]);
+    declare_size(&mut data, 100);
+
+    // The declared size is within the limits, but the decompressed data isn't
+    let limits = Limits::default().max_file_size(1000);
+    let mut archive = ZipArchive::with_options(
+        Cursor::new(data.as_slice()),
+        ReadOptions::default().limits(limits),
+    )
+    .unwrap();
+    let mut file = archive.by_name("zeros").unwrap();
+    let error = file.read_to_end(&mut Vec::new()).unwrap_err();
+    match error.get_ref().and_then(|e| e.downcast_ref::<ZipError>()) {
+        Some(ZipError::LimitExceeded(_)) => {}
+        _ => panic!("unexpected error: {:?}", error),
+    }
+
+    let limits = Limits::default().max_total_size(1000);
+    let mut archive = ZipArchive::with_options(
+        Cursor::new(data.as_slice()),
+        ReadOptions::default().limits(limits),
+    )
+    .unwrap();
+    let directory = std::env::temp_dir().join(format!("zip-limits-{}", std::process::id()));
+    assert_limit_exceeded(archive.extract(&directory));
+    let extracted = std::fs::metadata(directory.join("zeros")).unwrap().len();
+    assert!(extracted <= 1001);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(feature = "ppmd")]
+#[test]
+fn decompression_memory() {
+    use std::io::Read;
+
+    // The writer uses a model of 16 MiB
+    let data = make_archive(CompressionMethod::Ppmd, &[("text", 1000)]);
+    let open = |limits| {
+        let options = ReadOptions::default().limits(limits);
+        ZipArchive::with_options(Cursor::new(data.as_slice()), options).unwrap()
+    };
+
+    let mut archive = open(Limits::default().max_decompression_memory(16 << 20));
+    assert!(archive.verify().is_ok());
+
+    let mut archive = open(Limits::default().max_decompression_memory(1 << 20));
+    let mut file = archive.by_name("text").unwrap();
+    let error = file.read(&mut [0; 16]).unwrap_err();
+    match error.get_ref().and_then(|e| e.downcast_ref::<ZipError>()) {
+        Some(ZipError::LimitExceeded(_)) => {}
+        _ => panic!("unexpected error: {:?}", error),
+    }
+}
+
+#[test]
+fn overlapping_files() {
+    let valid = make_archive(CompressionMethod::Stored, &[("a", 100), ("b", 100)]);
+    let mut archive = ZipArchive::new(Cursor::new(valid.as_slice())).unwrap();
+    assert_eq!(archive.find_overlaps().unwrap(), vec![]);
+
+    // Both files use the data of the first one
+    let mut shared = valid.clone();
+    let header_start = central_header(&shared, 1) + 42;
+    shared[header_start..header_start + 4].copy_from_slice(&0u32.to_le_bytes());
+    let mut archive = ZipArchive::new(Cursor::new(shared.as_slice())).unwrap();
+    assert_eq!(archive.find_overlaps().unwrap(), vec![Overlap::File(1, 0)]);
+
+    let limits = Limits::default().reject_overlapping_files(true);
+    match ZipArchive::with_options(
+        Cursor::new(shared.as_slice()),
+        ReadOptions::default().limits(limits),
+    ) {
+        Err(ZipError::InvalidArchive(_)) => {}
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("overlapping files were accepted"),
+    }
+    assert!(ZipArchive::with_options(
+        Cursor::new(valid.as_slice()),
+        ReadOptions::default().limits(limits)
+    )
+    .is_ok());
+
+    // The second file extends into the central directory
+    let mut long = valid;
+    let compressed_size = central_header(&long, 1) + 20;
+    long[compressed_size..compressed_size + 4].copy_from_slice(&150u32.to_le_bytes());
+    let mut archive = ZipArchive::new(Cursor::new(long.as_slice())).unwrap();
+    assert_eq!(
+        archive.find_overlaps().unwrap(),
+        vec![Overlap::CentralDirectory(1)]
+    );
+}
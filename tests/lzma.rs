@@ -0,0 +1,53 @@
+#![cfg(feature = "lzma")]
+
+use std::io::{self, Read};
+use zip::read::{Limits, ReadOptions};
+use zip::result::ZipError;
+use zip::{CompressionMethod, ZipArchive};
+
+#[test]
+fn decompress_lzma() {
+    // Written by bsdtar (libarchive 3.8.2) with `--options zip:compression=lzma`, which ends
+    // the data with an end marker and writes a data descriptor
+    let mut v = Vec::new();
+    v.extend_from_slice(include_bytes!("data/lzma.zip"));
+    let mut archive = ZipArchive::new(io::Cursor::new(v)).expect("couldn't open test zip file");
+
+    let mut file = archive
+        .by_name("data.txt")
+        .expect("couldn't find file in archive");
+    assert_eq!("data.txt", file.name());
+    assert_eq!(CompressionMethod::Lzma, file.compression());
+
+    let mut content = Vec::new();
+    file.read_to_end(&mut content).expect("couldn't read file");
+    assert_eq!(
+        content,
+        b"The quick brown fox jumps over the lazy dog.\n".repeat(100)
+    );
+}
+
+#[test]
+fn dictionary_memory_limit() {
+    let mut v = include_bytes!("data/lzma.zip").to_vec();
+    let limits = Limits::default().max_decompression_memory(16 << 20);
+    let open = |v: Vec<u8>| {
+        let options = ReadOptions::default().limits(limits);
+        ZipArchive::with_options(io::Cursor::new(v), options).unwrap()
+    };
+
+    // The dictionary of 8 MiB fits within the limit
+    let mut archive = open(v.clone());
+    let data_start = archive.by_name("data.txt").unwrap().data_start() as usize;
+    assert!(archive.verify().is_ok());
+
+    // Declare a dictionary of 1 GiB in the properties, after the version and their length
+    v[data_start + 5..data_start + 9].copy_from_slice(&(1u32 << 30).to_le_bytes());
+    let mut archive = open(v);
+    let mut file = archive.by_name("data.txt").unwrap();
+    let error = file.read_to_end(&mut Vec::new()).unwrap_err();
+    match error.get_ref().and_then(|e| e.downcast_ref::<ZipError>()) {
+        Some(ZipError::LimitExceeded(_)) => {}
+        _ => panic!("unexpected error: {:?}", error),
+    }
+}
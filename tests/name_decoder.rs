@@ -0,0 +1,78 @@
+use std::io::Cursor;
+use zip::read::{read_zipfile_from_stream_with_options, ReadOptions};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// Build an archive with a file whose name is the given bytes, without the UTF-8 flag.
+fn archive_with_raw_name(name: &[u8]) -> Vec<u8> {
+    let placeholder = "x".repeat(name.len());
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(placeholder.as_str(), FileOptions::default())
+        .unwrap();
+    let mut data = zip.finish().unwrap().into_inner();
+    let positions: Vec<_> = data
+        .windows(name.len())
+        .enumerate()
+        .filter(|(_, window)| *window == placeholder.as_bytes())
+        .map(|(i, _)| i)
+        .collect();
+    for i in positions {
+        data[i..i + name.len()].copy_from_slice(name);
+    }
+    data
+}
+
+// "Анна" in CP866
+const CP866_NAME: &[u8] = b"\x80\xad\xad\xa0";
+
+#[test]
+fn default_cp437() {
+    let data = archive_with_raw_name(CP866_NAME);
+    let archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    assert_eq!(archive.file_names().collect::<Vec<_>>(), ["Ç¡¡á"]);
+}
+
+#[test]
+fn closure_decoder() {
+    let data = archive_with_raw_name(CP866_NAME);
+    let decoder = |raw: &[u8]| format!("{} bytes", raw.len());
+    let options = ReadOptions::default().name_decoder(&decoder);
+    let mut archive = ZipArchive::with_options(Cursor::new(data), options).unwrap();
+    assert_eq!(archive.by_index(0).unwrap().name(), "4 bytes");
+    assert_eq!(archive.by_index(0).unwrap().name_raw(), CP866_NAME);
+}
+
+#[test]
+fn decoder_without_central_directory() {
+    let data = archive_with_raw_name(CP866_NAME);
+    let decoder = |raw: &[u8]| format!("{} bytes", raw.len());
+    let options = ReadOptions::default().name_decoder(&decoder);
+
+    let (mut archive, _) = ZipArchive::recover_with_options(Cursor::new(&data), options).unwrap();
+    assert_eq!(archive.by_index(0).unwrap().name(), "4 bytes");
+
+    let mut reader = Cursor::new(&data);
+    let file = read_zipfile_from_stream_with_options(&mut reader, options)
+        .unwrap()
+        .unwrap();
+    assert_eq!(file.name(), "4 bytes");
+    assert_eq!(file.name_raw(), CP866_NAME);
+}
+
+#[cfg(feature = "encoding_rs")]
+#[test]
+fn encoding_rs_decoders() {
+    use zip::read::DetectEncoding;
+
+    let data = archive_with_raw_name(CP866_NAME);
+    let options = ReadOptions::default().name_decoder(&encoding_rs::IBM866);
+    let archive = ZipArchive::with_options(Cursor::new(data.as_slice()), options).unwrap();
+    assert_eq!(archive.file_names().collect::<Vec<_>>(), ["Анна"]);
+
+    // "日本語" in Shift-JIS
+    let data = archive_with_raw_name(b"\x93\xfa\x96\x7b\x8c\xea");
+    let decoder = DetectEncoding::new(&[encoding_rs::SHIFT_JIS, encoding_rs::IBM866]);
+    let options = ReadOptions::default().name_decoder(&decoder);
+    let mut archive = ZipArchive::with_options(Cursor::new(data), options).unwrap();
+    assert!(archive.by_name("日本語").is_ok());
+}
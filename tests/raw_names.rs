@@ -0,0 +1,107 @@
+use std::io::{Cursor, Read, Write};
+use zip::extra_fields::ExtraField;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+fn general_purpose_flag(data: &[u8], signature: &[u8]) -> u16 {
+    let start = data
+        .windows(4)
+        .position(|window| window == signature)
+        .unwrap();
+    // The flags follow the version needed to extract, which the central header precedes with
+    // the version made by
+    let offset = if signature == b"PK\x03\x04" { 6 } else { 8 };
+    u16::from_le_bytes([data[start + offset], data[start + offset + 1]])
+}
+
+fn assert_not_utf8(data: &[u8]) {
+    assert_eq!(general_purpose_flag(data, b"PK\x03\x04") & (1 << 11), 0);
+    assert_eq!(general_purpose_flag(data, b"PK\x01\x02") & (1 << 11), 0);
+}
+
+#[test]
+fn cp437_name() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().cp437_name(true);
+    zip.start_file("Curaçao.txt", options).unwrap();
+    zip.write_all(b"content").unwrap();
+    let data = zip.finish().unwrap().into_inner();
+    assert_not_utf8(&data);
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let file = archive.by_index(0).unwrap();
+    assert_eq!(file.name(), "Curaçao.txt");
+    assert_eq!(file.name_raw(), b"Cura\x87ao.txt");
+}
+
+#[test]
+fn cp437_name_falls_back_to_utf8() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().cp437_name(true);
+    zip.start_file("日本語.txt", options).unwrap();
+    let data = zip.finish().unwrap().into_inner();
+    assert_ne!(general_purpose_flag(&data, b"PK\x03\x04") & (1 << 11), 0);
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let file = archive.by_index(0).unwrap();
+    assert_eq!(file.name(), "日本語.txt");
+    assert_eq!(file.name_raw(), "日本語.txt".as_bytes());
+}
+
+#[test]
+fn raw_name() {
+    // "日本語.txt" in Shift-JIS
+    let raw_name: &[u8] = b"\x93\xfa\x96\x7b\x8c\xea.txt";
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file_raw_name(raw_name, FileOptions::default(), &[], &[])
+        .unwrap();
+    zip.write_all(b"content").unwrap();
+    let fields = [ExtraField::unicode_path(raw_name, "日本語.txt")];
+    zip.start_file_raw_name(raw_name, FileOptions::default(), &fields, &fields)
+        .unwrap();
+    let data = zip.finish().unwrap().into_inner();
+    assert_not_utf8(&data);
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let mut file = archive.by_index(0).unwrap();
+    assert_eq!(file.name_raw(), raw_name);
+    let mut content = String::new();
+    file.read_to_string(&mut content).unwrap();
+    assert_eq!(content, "content");
+    drop(file);
+
+    let file = archive.by_index(1).unwrap();
+    assert_eq!(file.name_raw(), raw_name);
+    assert_eq!(file.name(), "日本語.txt");
+}
+
+#[test]
+fn raw_copy_keeps_raw_name() {
+    let raw_name: &[u8] = b"\x93\xfa\x96\x7b\x8c\xea.txt";
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file_raw_name(raw_name, FileOptions::default(), &[], &[])
+        .unwrap();
+    zip.write_all(b"content").unwrap();
+    zip.start_file("日本語.txt", FileOptions::default())
+        .unwrap();
+    let data = zip.finish().unwrap().into_inner();
+
+    let mut source = ZipArchive::new(Cursor::new(data)).unwrap();
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.raw_copy_file(source.by_index(0).unwrap()).unwrap();
+    zip.raw_copy_file(source.by_index(1).unwrap()).unwrap();
+    let data = zip.finish().unwrap().into_inner();
+    assert_eq!(general_purpose_flag(&data, b"PK\x03\x04") & (1 << 11), 0);
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let mut file = archive.by_index(0).unwrap();
+    assert_eq!(file.name_raw(), raw_name);
+    let mut content = String::new();
+    file.read_to_string(&mut content).unwrap();
+    assert_eq!(content, "content");
+    drop(file);
+
+    let file = archive.by_index(1).unwrap();
+    assert_eq!(file.name(), "日本語.txt");
+    assert_eq!(file.name_raw(), "日本語.txt".as_bytes());
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use std::io::Read;
+use std::sync::Arc;
+use std::thread;
+use zip::read::ReadAtCursor;
+use zip::result::ZipError;
+use zip::write::FileOptions;
+use zip::ZipArchive;
+
+fn contents(i: usize) -> Vec<u8> {
+    format!("file {} ", i).repeat(1000 + i * 100).into_bytes()
+}
+
+fn make_archive() -> Vec<u8> {
+    let files: Vec<_> = (0..16)
+        .map(|i| (format!("{}.txt", i), contents(i)))
+        .collect();
+    let files: Vec<_> = files
+        .iter()
+        .map(|(name, content)| (name.as_str(), content.as_slice()))
+        .collect();
+    common::make_archive_with(FileOptions::default(), &files)
+}
+
+#[test]
+fn concurrent_reads() {
+    let archive = ZipArchive::new(ReadAtCursor::new(make_archive())).unwrap();
+    let archive = Arc::new(archive);
+
+    let threads: Vec<_> = (0..archive.len())
+        .map(|i| {
+            let archive = archive.clone();
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    let mut file = archive.by_index_shared(i).unwrap();
+                    let mut buf = Vec::new();
+                    file.read_to_end(&mut buf).unwrap();
+                    assert_eq!(buf, contents(i));
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn interleaved_reads() {
+    let data = make_archive();
+    let archive = ZipArchive::new(ReadAtCursor::new(data.as_slice())).unwrap();
+
+    let mut first = archive.by_name_shared("1.txt").unwrap();
+    let mut second = archive.by_name_shared("2.txt").unwrap();
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    let mut buf = [0; 100];
+    loop {
+        let count = first.read(&mut buf).unwrap();
+        a.extend_from_slice(&buf[..count]);
+        let other = second.read(&mut buf).unwrap();
+        b.extend_from_slice(&buf[..other]);
+        if count == 0 && other == 0 {
+            break;
+        }
+    }
+    assert_eq!(a, contents(1));
+    assert_eq!(b, contents(2));
+
+    assert!(matches!(
+        archive.by_name_shared("nonexistent"),
+        Err(ZipError::FileNotFound)
+    ));
+}
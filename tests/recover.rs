@@ -0,0 +1,89 @@
+mod common;
+
+use common::{find, make_archive, owned_files, read_all};
+use std::io::Cursor;
+use zip::ZipArchive;
+
+#[test]
+fn missing_central_directory() {
+    let files: &[(&str, &[u8])] = &[("a.txt", b"first file"), ("b.txt", b"second file")];
+    let mut data = make_archive(files);
+    let directory_start = find(&data, b"PK\x01\x02");
+    data.truncate(directory_start + 10);
+    assert!(ZipArchive::new(Cursor::new(data.as_slice())).is_err());
+
+    let (mut archive, skipped) = ZipArchive::recover(Cursor::new(data.as_slice())).unwrap();
+    assert_eq!(skipped, vec![]);
+    assert_eq!(read_all(&mut archive), owned_files(files));
+    assert_eq!(archive.by_name("b.txt").unwrap().size(), 11);
+}
+
+#[test]
+fn garbage_between_files() {
+    let first = make_archive(&[("a.txt", b"first file")]);
+    let second = make_archive(&[("b.txt", b"second file")]);
+    let first_end = find(&first, b"PK\x01\x02");
+    let second_end = find(&second, b"PK\x01\x02");
+
+    let mut data = first[..first_end].to_vec();
+    data.extend_from_slice(b"garbage PK\x03\x04 with a signature");
+    let garbage_end = data.len() as u64;
+    data.extend_from_slice(&second[..second_end]);
+    data.extend_from_slice(b"trailing");
+
+    let (mut archive, skipped) = ZipArchive::recover(Cursor::new(data.as_slice())).unwrap();
+    assert_eq!(
+        skipped,
+        vec![
+            first_end as u64..garbage_end,
+            data.len() as u64 - 8..data.len() as u64
+        ]
+    );
+    assert_eq!(
+        read_all(&mut archive),
+        vec![
+            ("a.txt".to_owned(), b"first file".to_vec()),
+            ("b.txt".to_owned(), b"second file".to_vec()),
+        ]
+    );
+}
+
+/// Build a stored file whose sizes and CRC-32 are only in a data descriptor.
+fn file_with_data_descriptor(name: &str, content: &[u8]) -> Vec<u8> {
+    let crc32 = crc32fast::hash(content);
+    let mut data = Vec::new();
+    data.extend_from_slice(b"PK\x03\x04");
+    data.extend_from_slice(&20u16.to_le_bytes()); // version needed
+    data.extend_from_slice(&0x08u16.to_le_bytes()); // flags
+    data.extend_from_slice(&0u16.to_le_bytes()); // compression method
+    data.extend_from_slice(&0u16.to_le_bytes()); // time
+    data.extend_from_slice(&0x21u16.to_le_bytes()); // date
+    data.extend_from_slice(&[0; 12]); // CRC-32 and sizes
+    data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    data.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+    data.extend_from_slice(name.as_bytes());
+    data.extend_from_slice(content);
+    data.extend_from_slice(b"PK\x07\x08");
+    data.extend_from_slice(&crc32.to_le_bytes());
+    data.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    data.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    data
+}
+
+#[test]
+fn data_descriptor() {
+    // The content contains a signature whose sizes don't match
+    let content = b"contains PK\x07\x08 in its data";
+    let mut data = file_with_data_descriptor("a.txt", content);
+    data.extend(file_with_data_descriptor("b.txt", b"second file"));
+
+    let (mut archive, skipped) = ZipArchive::recover(Cursor::new(data.as_slice())).unwrap();
+    assert_eq!(skipped, vec![]);
+    assert_eq!(
+        read_all(&mut archive),
+        vec![
+            ("a.txt".to_owned(), content.to_vec()),
+            ("b.txt".to_owned(), b"second file".to_vec()),
+        ]
+    );
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::{find, read, rfind};
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+fn make_archive(method: CompressionMethod, files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(method);
+    for &(name, content) in files {
+        zip.start_file(name, options).unwrap();
+        zip.write_all(content).unwrap();
+    }
+    zip.set_comment("comment");
+    zip.finish().unwrap().into_inner()
+}
+
+fn repair(data: &[u8]) -> (zip::read::RepairReport, ZipArchive<Cursor<Vec<u8>>>) {
+    let mut output = Cursor::new(Vec::new());
+    let report = ZipArchive::repair(Cursor::new(data), &mut output).unwrap();
+    let mut archive = ZipArchive::new(output).unwrap();
+    assert!(archive.verify().is_ok());
+    (report, archive)
+}
+
+const FILES: &[(&str, &[u8])] = &[("a.txt", b"first file"), ("b.txt", b"second file")];
+
+#[test]
+fn trailing_garbage() {
+    let mut data = make_archive(CompressionMethod::Stored, FILES);
+    data.extend_from_slice(b"trailing garbage");
+
+    let (report, mut archive) = repair(&data);
+    assert!(!report.scanned());
+    assert_eq!(report.copied(), ["a.txt", "b.txt"]);
+    assert_eq!(archive.comment(), b"comment");
+    assert_eq!(read(&mut archive, "b.txt"), b"second file");
+}
+
+#[test]
+fn bad_offset() {
+    let mut data = make_archive(CompressionMethod::Stored, FILES);
+    let central = rfind(&data, b"PK\x01\x02");
+    data[central + 42..central + 46].copy_from_slice(&1u32.to_le_bytes());
+    assert!(ZipArchive::new(Cursor::new(data.as_slice()))
+        .unwrap()
+        .by_name("b.txt")
+        .is_err());
+
+    let (report, mut archive) = repair(&data);
+    assert!(report.scanned());
+    assert!(report.failures().is_empty());
+    assert_eq!(report.copied(), ["a.txt", "b.txt"]);
+    assert_eq!(read(&mut archive, "a.txt"), b"first file");
+    assert_eq!(read(&mut archive, "b.txt"), b"second file");
+}
+
+#[test]
+fn missing_central_directory() {
+    let mut data = make_archive(CompressionMethod::Stored, FILES);
+    let directory_start = find(&data, b"PK\x01\x02");
+    data.truncate(directory_start);
+
+    let (report, mut archive) = repair(&data);
+    assert!(report.scanned());
+    assert_eq!(report.skipped(), []);
+    assert_eq!(archive.len(), 2);
+    assert_eq!(read(&mut archive, "a.txt"), b"first file");
+}
+
+#[test]
+fn wrong_count() {
+    let files: &[(&str, &[u8])] = &[("a", b"first"), ("b", b"second"), ("c", b"third")];
+    let mut data = make_archive(CompressionMethod::Stored, files);
+    let footer = rfind(&data, b"PK\x05\x06");
+    data[footer + 8..footer + 10].copy_from_slice(&1u16.to_le_bytes());
+    data[footer + 10..footer + 12].copy_from_slice(&1u16.to_le_bytes());
+    assert_eq!(
+        ZipArchive::new(Cursor::new(data.as_slice())).unwrap().len(),
+        1
+    );
+
+    let (report, mut archive) = repair(&data);
+    assert!(report.scanned());
+    assert!(report.failures().is_empty());
+    assert_eq!(report.copied(), ["a", "b", "c"]);
+    assert_eq!(read(&mut archive, "c"), b"third");
+}
+
+#[cfg(any(
+    feature = "deflate",
+    feature = "deflate-miniz",
+    feature = "deflate-zlib"
+))]
+#[test]
+fn damaged_local_header() {
+    let content = b"compressible content ".repeat(100);
+    let files: &[(&str, &[u8])] = &[
+        ("a.txt", &content),
+        ("b.txt", &content),
+        ("c.txt", &content),
+    ];
+    let mut data = make_archive(CompressionMethod::Deflated, files);
+    let second_header = common::find_nth(&data, b"PK\x03\x04", 1);
+    data[second_header] = 0;
+
+    let (report, mut archive) = repair(&data);
+    assert!(!report.scanned());
+    assert_eq!(report.copied(), ["a.txt", "c.txt"]);
+    assert_eq!(report.failures().len(), 1);
+    assert_eq!(report.failures()[0].0, "b.txt");
+    assert!(matches!(
+        report.failures()[0].1,
+        zip::result::ZipError::InvalidArchive(_)
+    ));
+    assert_eq!(
+        archive.by_name("c.txt").unwrap().compression(),
+        CompressionMethod::Deflated
+    );
+    assert_eq!(read(&mut archive, "c.txt"), content);
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use common::{find, make_archive_with, owned_files};
+use std::io::{Cursor, Read, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Cut an archive off before its central directory, as if it wasn't finished.
+fn local_files(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let options = FileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+    let mut data = make_archive_with(options, files);
+    let directory_start = find(&data, b"PK\x01\x02");
+    data.truncate(directory_start);
+    data
+}
+
+/// Resume writing an unfinished archive, add a file and return the contents of the result.
+fn resume_and_finish(data: Vec<u8>) -> Vec<(String, Vec<u8>)> {
+    let mut zip = ZipWriter::resume(Cursor::new(data)).unwrap();
+    zip.start_file("new.txt", FileOptions::default()).unwrap();
+    zip.write_all(b"new file").unwrap();
+    let data = zip.finish().unwrap().into_inner();
+    let mut archive = ZipArchive::new(Cursor::new(data.as_slice())).unwrap();
+    assert!(archive.verify().is_ok());
+    assert_eq!(archive.find_overlaps().unwrap(), vec![]);
+
+    // The discarded data was truncated, so the files follow each other directly and the central
+    // directory and its end follow the last file
+    let mut end = 0;
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i).unwrap();
+        assert_eq!(file.header_start(), end);
+        end = file.data_start() + file.compressed_size();
+    }
+    assert_eq!(archive.by_index(0).unwrap().central_header_start(), end);
+    let footers = data.windows(4).filter(|window| *window == b"PK\x05\x06");
+    assert_eq!(footers.count(), 1);
+
+    (0..archive.len())
+        .map(|i| {
+            let mut file = archive.by_index(i).unwrap();
+            // The ZIP64 extra field of the local header isn't copied
+            assert_eq!(file.extra_data(), []);
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).unwrap();
+            (file.name().to_owned(), content)
+        })
+        .collect()
+}
+
+#[test]
+fn complete_files() {
+    let data = local_files(&[("a.txt", b"first file"), ("b.txt", b"second file")]);
+    assert_eq!(
+        resume_and_finish(data),
+        owned_files(&[
+            ("a.txt", b"first file"),
+            ("b.txt", b"second file"),
+            ("new.txt", b"new file"),
+        ])
+    );
+}
+
+#[test]
+fn unfinished_file() {
+    let mut data = local_files(&[("a.txt", b"first file")]);
+    // A file whose local header wasn't updated yet, since its data wasn't finished
+    let mut unfinished = local_files(&[("b.txt", b"the second file is much longer")]);
+    unfinished[14..26].copy_from_slice(&[0; 12]);
+    unfinished.truncate(unfinished.len() - 10);
+    data.extend_from_slice(&unfinished);
+
+    assert_eq!(
+        resume_and_finish(data),
+        owned_files(&[("a.txt", b"first file"), ("new.txt", b"new file")])
+    );
+}
+
+#[test]
+fn truncated_file() {
+    let mut data = local_files(&[("a.txt", b"first file"), ("b.txt", b"second file")]);
+    data.truncate(data.len() - 5);
+
+    assert_eq!(
+        resume_and_finish(data),
+        owned_files(&[("a.txt", b"first file"), ("new.txt", b"new file")])
+    );
+}
+
+#[test]
+fn damaged_earlier_file() {
+    let mut data = local_files(&[
+        ("a.txt", b"first file"),
+        ("b.txt", b"second file"),
+        ("c.txt", b"third file"),
+    ]);
+    // Corrupt the second file, so that its CRC-32 doesn't match
+    let position = find(&data, b"second file");
+    data[position] = b'S';
+
+    assert_eq!(
+        resume_and_finish(data),
+        owned_files(&[("a.txt", b"first file"), ("new.txt", b"new file")])
+    );
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::find;
+use std::io::Cursor;
+use zip::read::VerifyProblem;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+fn make_archive() -> Vec<u8> {
+    common::make_archive(&[("first.txt", b"first file"), ("second.txt", b"second file")])
+}
+
+#[test]
+fn valid_archives() {
+    for data in &[
+        make_archive(),
+        include_bytes!("data/mimetype.zip").to_vec(),
+        include_bytes!("data/files_and_dirs.zip").to_vec(),
+        include_bytes!("data/zip64_demo.zip").to_vec(),
+    ] {
+        let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+        let report = archive.verify();
+        assert!(report.is_ok(), "{:?}", report);
+        assert_eq!(report.files().len(), archive.len());
+    }
+}
+
+#[test]
+fn corrupted_data() {
+    let mut data = make_archive();
+    let position = find(&data, b"second file");
+    data[position] = b'S';
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let report = archive.verify();
+    assert!(!report.is_ok());
+    assert!(report.files()[0].is_ok());
+    let failures: Vec<_> = report.failures().collect();
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].index(), 1);
+    assert_eq!(failures[0].name(), "second.txt");
+    match failures[0].problems() {
+        [VerifyProblem::Error(ZipError::Io(_))] => {}
+        problems => panic!("unexpected problems: {:?}", problems),
+    }
+}
+
+#[test]
+fn local_header_mismatch() {
+    let mut data = make_archive();
+    // Rename the first file in its local header only
+    let position = find(&data, b"first.txt");
+    data[position] = b'F';
+
+    let mut archive = ZipArchive::new(Cursor::new(data)).unwrap();
+    let report = archive.verify();
+    match report.files()[0].problems() {
+        [VerifyProblem::LocalHeaderMismatch("file name")] => {}
+        problems => panic!("unexpected problems: {:?}", problems),
+    }
+    assert!(report.files()[1].is_ok());
+}
+
+#[cfg(feature = "aes-crypto")]
+#[test]
+fn encrypted() {
+    let data = include_bytes!("data/aes_archive.zip");
+    let mut archive = ZipArchive::new(Cursor::new(&data[..])).unwrap();
+    assert!(archive.verify_decrypt(b"helloworld").is_ok());
+
+    let report = archive.verify_decrypt(b"wrong password");
+    assert!(report
+        .files()
+        .iter()
+        .all(|file| matches!(file.problems(), [VerifyProblem::InvalidPassword])));
+
+    let report = archive.verify();
+    assert!(report.files().iter().all(|file| matches!(
+        file.problems(),
+        [VerifyProblem::Error(ZipError::UnsupportedArchive(
+            ZipError::PASSWORD_REQUIRED
+        ))]
+    )));
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "xz")]
+
+use std::io::{self, Read};
+use zip::read::{Limits, ReadOptions};
+use zip::result::ZipError;
+use zip::{CompressionMethod, ZipArchive};
+
+#[test]
+fn decompress_xz() {
+    // Written by bsdtar (libarchive 3.8.2) with `--options zip:compression=xz`, which writes a
+    // data descriptor
+    let mut v = Vec::new();
+    v.extend_from_slice(include_bytes!("data/xz.zip"));
+    let mut archive = ZipArchive::new(io::Cursor::new(v)).expect("couldn't open test zip file");
+
+    let mut file = archive
+        .by_name("data.txt")
+        .expect("couldn't find file in archive");
+    assert_eq!("data.txt", file.name());
+    assert_eq!(CompressionMethod::Xz, file.compression());
+
+    let mut content = Vec::new();
+    file.read_to_end(&mut content).expect("couldn't read file");
+    assert_eq!(
+        content,
+        b"The quick brown fox jumps over the lazy dog.\n".repeat(100)
+    );
+}
+
+#[test]
+fn dictionary_memory_limit() {
+    let open = |limit| {
+        let limits = Limits::default().max_decompression_memory(limit);
+        let v = include_bytes!("data/xz.zip").to_vec();
+        ZipArchive::with_options(io::Cursor::new(v), ReadOptions::default().limits(limits)).unwrap()
+    };
+
+    // The block header declares a dictionary of 8 MiB
+    let mut archive = open(16 << 20);
+    assert!(archive.verify().is_ok());
+
+    let mut archive = open(1 << 20);
+    let mut file = archive.by_name("data.txt").unwrap();
+    let error = file.read_to_end(&mut Vec::new()).unwrap_err();
+    match error.get_ref().and_then(|e| e.downcast_ref::<ZipError>()) {
+        Some(ZipError::LimitExceeded(_)) => {}
+        _ => panic!("unexpected error: {:?}", error),
+    }
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "zstd")]
+
+use std::io::{Cursor, Read, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+fn sample(i: usize) -> String {
+    format!(
+        r#"{{"id":{},"name":"user-{}","active":{},"roles":["reader","writer"],"score":{}}}"#,
+        i,
+        i * 7,
+        i % 3 == 1,
+        i * 31 % 100
+    )
+}
+
+fn read_to_string<R: Read + std::io::Seek>(archive: &mut ZipArchive<R>, name: &str) -> String {
+    let mut contents = String::new();
+    archive
+        .by_name(name)
+        .unwrap()
+        .read_to_string(&mut contents)
+        .unwrap();
+    contents
+}
+
+#[test]
+fn raw_content_dictionary() {
+    let dictionary = sample(0).into_bytes();
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Zstd);
+    zip.set_zstd_dictionary(&dictionary).unwrap();
+    zip.start_file("1.json", options).unwrap();
+    zip.write_all(sample(1).as_bytes()).unwrap();
+    zip.set_zstd_dictionary(&[]).unwrap();
+    zip.start_file("plain.json", options).unwrap();
+    zip.write_all(sample(2).as_bytes()).unwrap();
+    let archive = zip.finish().unwrap();
+
+    let mut archive = ZipArchive::new(archive).unwrap();
+    let mut contents = Vec::new();
+    assert!(archive
+        .by_name("1.json")
+        .unwrap()
+        .read_to_end(&mut contents)
+        .is_err());
+
+    archive.add_zstd_dictionary(&dictionary);
+    assert_eq!(read_to_string(&mut archive, "1.json"), sample(1));
+    assert_eq!(read_to_string(&mut archive, "plain.json"), sample(2));
+}
+
+#[test]
+fn trained_dictionary_stored_in_archive() {
+    let samples: Vec<_> = (0..1000).map(sample).collect();
+    let dictionary = zstd::dict::from_samples(&samples, 4096).unwrap();
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(
+        "dictionary",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )
+    .unwrap();
+    zip.write_all(&dictionary).unwrap();
+    zip.set_zstd_dictionary(&dictionary).unwrap();
+    let options = FileOptions::default().compression_method(CompressionMethod::Zstd);
+    for (i, sample) in samples.iter().enumerate().take(10) {
+        zip.start_file(format!("{}.json", i), options).unwrap();
+        zip.write_all(sample.as_bytes()).unwrap();
+    }
+    let archive = zip.finish().unwrap();
+
+    let mut archive = ZipArchive::new(archive).unwrap();
+    archive.add_zstd_dictionary_by_name("dictionary").unwrap();
+    for (i, sample) in samples.iter().enumerate().take(10) {
+        let name = format!("{}.json", i);
+        assert!(archive.by_name(&name).unwrap().compressed_size() < sample.len() as u64);
+        assert_eq!(&read_to_string(&mut archive, &name), sample);
+    }
+}